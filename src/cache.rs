@@ -0,0 +1,171 @@
+use crate::{
+    fetch::{Fetch, Headers, HttpFetch, Response},
+    record::RecordDir,
+};
+use log::warn;
+use reqwest::{Error, IntoUrl};
+use std::{io, path::PathBuf, sync::Arc};
+
+// Wraps HttpFetch with an on-disk cache. Responses with an ETag or Last-Modified header are
+// stored, and later requests for the same URL are made conditional. A 304 is answered with
+// the cached response, marked as unchanged.
+#[derive(Clone)]
+pub struct HttpCache {
+    http: HttpFetch,
+    records: Option<Arc<RecordDir>>,
+}
+
+impl HttpCache {
+    pub fn with_dir(http: HttpFetch, path: impl Into<PathBuf>) -> io::Result<HttpCache> {
+        Ok(HttpCache {
+            http,
+            records: Some(Arc::new(RecordDir::new(path)?)),
+        })
+    }
+
+    fn cached(&self, url: &str) -> Option<Response> {
+        let records = self.records.as_ref()?;
+
+        match records.get(url) {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!("Error reading cached response for URL {} - {:?}", url, e);
+                None
+            }
+        }
+    }
+
+    fn store(&self, url: &str, response: &Response) {
+        let Some(records) = &self.records else {
+            return;
+        };
+
+        if response.status != 200 || !has_validators(response) {
+            return;
+        }
+
+        // Stored under the requested URL, which differs from response.url after a redirect
+        let response = Response {
+            url: url.to_owned(),
+            ..response.clone()
+        };
+
+        if let Err(e) = records.put(&response) {
+            warn!("Error caching response for URL {} - {:?}", url, e);
+        }
+    }
+}
+
+fn has_validators(response: &Response) -> bool {
+    response.header("etag").is_some() || response.header("last-modified").is_some()
+}
+
+pub fn conditional_headers(cached: &Response) -> Headers {
+    let mut headers = vec![];
+
+    if let Some(etag) = cached.header("etag") {
+        headers.push(("if-none-match".to_owned(), etag.to_owned()));
+    }
+    if let Some(last_modified) = cached.header("last-modified") {
+        headers.push(("if-modified-since".to_owned(), last_modified.to_owned()));
+    }
+
+    headers
+}
+
+impl Fetch for HttpCache {
+    // Without a cache directory every request goes to the network
+    fn new() -> HttpCache {
+        HttpCache {
+            http: HttpFetch::new(),
+            records: None,
+        }
+    }
+
+    async fn get<T: IntoUrl + AsRef<str> + Send>(&self, url: T) -> Result<Response, Error> {
+        let requested_url = url.as_ref().to_owned();
+        let cached = self.cached(&requested_url);
+        let headers = cached.as_ref().map(conditional_headers).unwrap_or_default();
+
+        let response = self.http.get_with_headers(url, &headers).await?;
+
+        if let (304, Some(cached)) = (response.status, cached) {
+            return Ok(Response {
+                request_headers: response.request_headers,
+                elapsed: response.elapsed,
+                unchanged: true,
+                ..cached
+            });
+        }
+
+        self.store(&requested_url, &response);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::HttpCache;
+    use crate::fetch::{Fetch, HttpFetch};
+    use std::fs;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn http_cache_revalidates_and_reuses_cached_body_on_304() {
+        let mock_server = MockServer::start().await;
+        let path = std::env::temp_dir().join(format!("cache-{}", Uuid::new_v4()));
+        let cache = HttpCache::with_dir(HttpFetch::new(), &path).unwrap();
+
+        Mock::given(method("GET"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_string("Hello"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let first = cache.get(mock_server.uri()).await.unwrap();
+        let second = cache.get(mock_server.uri()).await.unwrap();
+
+        fs::remove_dir_all(path).unwrap();
+
+        assert!(!first.unchanged);
+        assert!(second.unchanged);
+        assert_eq!(second.status, 200);
+        assert_eq!(second.text(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn http_cache_does_not_store_responses_without_validators() {
+        let mock_server = MockServer::start().await;
+        let path = std::env::temp_dir().join(format!("cache-{}", Uuid::new_v4()));
+        let cache = HttpCache::with_dir(HttpFetch::new(), &path).unwrap();
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Hello"))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        cache.get(mock_server.uri()).await.unwrap();
+        let second = cache.get(mock_server.uri()).await.unwrap();
+
+        assert!(!second.unchanged);
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 0);
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
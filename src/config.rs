@@ -0,0 +1,392 @@
+use crate::priority_frontier::FrontierOrder;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+use url::Url;
+
+// A crawl job, e.g. crawl.toml. Every key is optional and maps to the command line flag
+// of the same name, paths are relative to the working directory. Flags on the command line
+// take precedence: seeds given there replace `seeds` and the [[site]] seeds, and switches
+// such as `report = true` are turned off with --no-report. The delay and max_pages of a
+// [[site]] can't be overridden.
+//
+//     seeds = ["https://example.com/"]
+//
+//     [politeness]
+//     delay = 1
+//
+//     [[site]]
+//     seed = "https://example.org/"
+//     max_pages = 500
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrawlConfig {
+    #[serde(default)]
+    pub seeds: Vec<String>,
+    pub seeds_file: Option<String>,
+    #[serde(default)]
+    pub scope: ScopeConfig,
+    #[serde(default)]
+    pub fetch: FetchConfig,
+    #[serde(default)]
+    pub politeness: PolitenessConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub extract: ExtractConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    // Sites crawled on top of `seeds`, with their own politeness and budget
+    #[serde(default, rename = "site")]
+    pub sites: Vec<SiteConfig>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopeConfig {
+    pub strip_tracking: Option<bool>,
+    pub strip_params: Option<Vec<String>>,
+    pub keep_params: Option<Vec<String>>,
+    pub ignore_params: Option<Vec<String>>,
+    pub external_links: Option<bool>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FetchConfig {
+    pub workers: Option<u8>,
+    pub frontier: Option<FrontierOrder>,
+    pub spill_dir: Option<String>,
+    pub frontier_buffer: Option<usize>,
+    pub bloom: Option<f64>,
+    pub bloom_capacity: Option<usize>,
+    pub cache: Option<String>,
+    pub replay: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolitenessConfig {
+    pub delay: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_pages: Option<usize>,
+    pub traps: Option<bool>,
+    pub max_url_length: Option<usize>,
+    pub max_repeated_segments: Option<usize>,
+    pub max_query_variants: Option<usize>,
+    pub max_pages_per_directory: Option<usize>,
+    pub depth_threshold: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractConfig {
+    pub keywords: Option<Vec<String>>,
+    pub profile: Option<String>,
+    pub relevance_threshold: Option<f64>,
+    pub max_irrelevant: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    pub output: Option<String>,
+    pub warc: Option<String>,
+    pub warc_max_size: Option<u64>,
+    pub record: Option<String>,
+    pub db: Option<String>,
+    pub since: Option<String>,
+    pub changeset: Option<String>,
+    pub report: Option<bool>,
+    pub print: Option<bool>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SiteConfig {
+    pub seed: String,
+    pub delay: Option<u64>,
+    pub max_pages: Option<usize>,
+}
+
+fn flag(args: &mut Vec<String>, name: &str, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        args.push(format!("--{}", name));
+        args.push(value.to_string());
+    }
+}
+
+fn switch(args: &mut Vec<String>, name: &str, value: Option<bool>) {
+    if value == Some(true) {
+        args.push(format!("--{}", name));
+    }
+}
+
+fn list(args: &mut Vec<String>, name: &str, values: &Option<Vec<String>>) {
+    flag(args, name, values.as_ref().map(|values| values.join(",")));
+}
+
+// A URL, a local directory to crawl as file:/// or - for stdin, like --url and --seeds take
+fn is_seed(seed: &str) -> bool {
+    seed == "-" || Url::parse(seed).is_ok() || Path::new(seed).is_dir()
+}
+
+impl CrawlConfig {
+    pub fn parse(toml: &str) -> Result<Self> {
+        let config: CrawlConfig =
+            toml::from_str(toml).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        CrawlConfig::parse(&fs::read_to_string(path)?)
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    // Checks what the types alone can't, so that a job fails before anything is crawled
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(Error::new(ErrorKind::InvalidData, message));
+
+        if let Some(bloom) = self
+            .fetch
+            .bloom
+            .filter(|bloom| !(*bloom > 0.0 && *bloom < 1.0))
+        {
+            return invalid(format!(
+                "fetch.bloom must be between 0 and 1, not {}",
+                bloom
+            ));
+        }
+        if let Some(threshold) = self
+            .extract
+            .relevance_threshold
+            .filter(|threshold| !(0.0..=1.0).contains(threshold))
+        {
+            return invalid(format!(
+                "extract.relevance_threshold must be between 0 and 1, not {}",
+                threshold
+            ));
+        }
+        if self.fetch.frontier_buffer == Some(0) {
+            return invalid("fetch.frontier_buffer must be at least 1".to_owned());
+        }
+        if self.limits.max_pages == Some(0) {
+            return invalid("limits.max_pages must be at least 1".to_owned());
+        }
+        if let Some(seed) = self.seeds.iter().find(|seed| seed.trim().is_empty()) {
+            return invalid(format!("seeds contains an empty seed {:?}", seed));
+        }
+        if let Some(seed) = self.seeds.iter().find(|seed| !is_seed(seed)) {
+            return invalid(format!("seeds contains an invalid URL {:?}", seed));
+        }
+
+        let mut sites = HashSet::new();
+        for site in &self.sites {
+            if site.seed.trim().is_empty() {
+                return invalid("site.seed must not be empty".to_owned());
+            }
+            if !is_seed(&site.seed) {
+                return invalid(format!("site.seed {:?} is not a valid URL", site.seed));
+            }
+            if !sites.insert(&site.seed) {
+                return invalid(format!("site {} is listed more than once", site.seed));
+            }
+            if site.max_pages == Some(0) {
+                return invalid(format!("site {}: max_pages must be at least 1", site.seed));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn site(&self, seed: &str) -> Option<&SiteConfig> {
+        self.sites.iter().find(|site| site.seed == seed)
+    }
+
+    // The seeds of the file as command line arguments, left out when the command line has
+    // seeds of its own
+    pub fn seed_args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        let seeds = self
+            .seeds
+            .iter()
+            .chain(self.sites.iter().map(|site| &site.seed))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !seeds.is_empty() {
+            list(&mut args, "seeds", &Some(seeds));
+        }
+        flag(&mut args, "seeds-file", self.seeds_file.as_ref());
+
+        args
+    }
+
+    // The rest of the file as command line arguments. They go before the real ones, which
+    // override them since every flag may be given more than once.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        let scope = &self.scope;
+        switch(&mut args, "strip-tracking", scope.strip_tracking);
+        list(&mut args, "strip-params", &scope.strip_params);
+        list(&mut args, "keep-params", &scope.keep_params);
+        list(&mut args, "ignore-params", &scope.ignore_params);
+        switch(&mut args, "external-links", scope.external_links);
+
+        let fetch = &self.fetch;
+        flag(&mut args, "workers-n", fetch.workers);
+        flag(&mut args, "frontier", fetch.frontier);
+        flag(&mut args, "spill-dir", fetch.spill_dir.as_ref());
+        flag(&mut args, "frontier-buffer", fetch.frontier_buffer);
+        flag(&mut args, "bloom", fetch.bloom);
+        flag(&mut args, "bloom-capacity", fetch.bloom_capacity);
+        flag(&mut args, "cache", fetch.cache.as_ref());
+        flag(&mut args, "replay", fetch.replay.as_ref());
+
+        flag(&mut args, "delay", self.politeness.delay);
+
+        let limits = &self.limits;
+        flag(&mut args, "max-pages", limits.max_pages);
+        switch(&mut args, "traps", limits.traps);
+        flag(&mut args, "max-url-length", limits.max_url_length);
+        flag(
+            &mut args,
+            "max-repeated-segments",
+            limits.max_repeated_segments,
+        );
+        flag(&mut args, "max-query-variants", limits.max_query_variants);
+        flag(
+            &mut args,
+            "max-pages-per-directory",
+            limits.max_pages_per_directory,
+        );
+        flag(&mut args, "depth-threshold", limits.depth_threshold);
+
+        let extract = &self.extract;
+        list(&mut args, "keywords", &extract.keywords);
+        flag(&mut args, "profile", extract.profile.as_ref());
+        flag(
+            &mut args,
+            "relevance-threshold",
+            extract.relevance_threshold,
+        );
+        flag(&mut args, "max-irrelevant", extract.max_irrelevant);
+
+        let output = &self.output;
+        flag(&mut args, "output", output.output.as_ref());
+        flag(&mut args, "warc", output.warc.as_ref());
+        flag(&mut args, "warc-max-size", output.warc_max_size);
+        flag(&mut args, "record", output.record.as_ref());
+        flag(&mut args, "db", output.db.as_ref());
+        flag(&mut args, "since", output.since.as_ref());
+        flag(&mut args, "changeset", output.changeset.as_ref());
+        switch(&mut args, "report", output.report);
+        switch(&mut args, "print", output.print);
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::{CrawlConfig, SiteConfig};
+
+    #[test]
+    fn crawl_config_turns_into_command_line_arguments() {
+        let config = CrawlConfig::parse(
+            r#"
+            seeds = ["https://example.com/"]
+
+            [scope]
+            strip_tracking = true
+            ignore_params = ["ref", "sort"]
+
+            [fetch]
+            frontier = "bfs"
+
+            [politeness]
+            delay = 1
+
+            [limits]
+            traps = true
+
+            [[site]]
+            seed = "https://example.org/"
+            max_pages = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.seed_args(),
+            vec!["--seeds", "https://example.com/,https://example.org/"]
+        );
+        assert_eq!(
+            config.to_args(),
+            vec![
+                "--strip-tracking",
+                "--ignore-params",
+                "ref,sort",
+                "--frontier",
+                "bfs",
+                "--delay",
+                "1",
+                "--traps",
+            ]
+        );
+        assert_eq!(
+            config.site("https://example.org/"),
+            Some(&SiteConfig {
+                seed: "https://example.org/".to_owned(),
+                delay: None,
+                max_pages: Some(10),
+            })
+        );
+    }
+
+    #[test]
+    fn crawl_config_rejects_unknown_keys_and_invalid_values() {
+        let unknown = CrawlConfig::parse("[politeness]\ndelays = 1\n").unwrap_err();
+        let wrong_type = CrawlConfig::parse("[politeness]\ndelay = \"1s\"\n").unwrap_err();
+        let invalid = CrawlConfig::parse("[fetch]\nbloom = 2.0\n").unwrap_err();
+        let duplicate = CrawlConfig::parse(
+            "[[site]]\nseed = \"https://a.com/\"\n[[site]]\nseed = \"https://a.com/\"\n",
+        )
+        .unwrap_err();
+        let seed = CrawlConfig::parse("seeds = [\"example.com\"]\n").unwrap_err();
+        let site_seed = CrawlConfig::parse("[[site]]\nseed = \"http//a.com/\"\n").unwrap_err();
+        let frontier = CrawlConfig::parse("[fetch]\nfrontier = \"random\"\n").unwrap_err();
+
+        assert!(unknown.to_string().contains("unknown field `delays`"));
+        assert!(wrong_type.to_string().contains("invalid type"));
+        assert_eq!(
+            invalid.to_string(),
+            "fetch.bloom must be between 0 and 1, not 2"
+        );
+        assert_eq!(
+            duplicate.to_string(),
+            "site https://a.com/ is listed more than once"
+        );
+        assert_eq!(
+            seed.to_string(),
+            "seeds contains an invalid URL \"example.com\""
+        );
+        assert_eq!(
+            site_seed.to_string(),
+            "site.seed \"http//a.com/\" is not a valid URL"
+        );
+        assert!(frontier.to_string().contains("unknown variant `random`"));
+    }
+}
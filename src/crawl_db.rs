@@ -0,0 +1,181 @@
+use crate::{
+    data_store::{DataStore, DataStoreEntry, Store},
+    dependencies::DepsConcrete,
+    site_frontier::SiteStats,
+    spilling_frontier::FrontierMetrics,
+    trap::TrapTrip,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+// A crawl persisted to disk: every entry of the data store plus what is needed to
+// re-crawl incrementally later on
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct CrawlDb {
+    pub seed: String,
+    // Every seed of a crawl started from a seed list, `seed` is the first of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seeds: Vec<String>,
+    pub pages: BTreeMap<String, DataStoreEntry<String>>,
+    pub sitemap: BTreeMap<String, Option<String>>,
+    pub cache: Option<String>,
+    #[serde(default)]
+    pub traps: Vec<TrapTrip>,
+    // URLs rewritten by query parameter rules, mapped to the URL as first found
+    #[serde(default)]
+    pub originals: BTreeMap<String, String>,
+    // Requests and budget of every site of a multi-site crawl, by seed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sites: BTreeMap<String, SiteStats>,
+    // Where the frontier stood at the end of a crawl that spilled it to disk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frontier: Option<FrontierMetrics>,
+}
+
+impl CrawlDb {
+    pub fn from_store(seed: &str, data_store: &(impl DataStore<String, String> + ?Sized)) -> Self {
+        let pages = data_store
+            .keys()
+            .into_iter()
+            .filter_map(|key| {
+                let entry = data_store.get(&key)?.clone();
+                Some((key, entry))
+            })
+            .collect();
+
+        CrawlDb {
+            seed: seed.to_owned(),
+            pages,
+            ..CrawlDb::default()
+        }
+    }
+
+    // Everything a crawl of `seeds` left in its dependencies
+    pub async fn from_dependencies(seeds: &[String], deps: &DepsConcrete) -> Self {
+        let traps = match &deps.traps {
+            Some(traps) => traps.read().await.trips().to_vec(),
+            None => vec![],
+        };
+        let sites = match &deps.sites {
+            Some(sites) => sites.read().await.stats(),
+            None => BTreeMap::new(),
+        };
+        let frontier = deps.url_frontier.read().await.metrics();
+
+        CrawlDb {
+            seeds: if seeds.len() > 1 {
+                seeds.to_vec()
+            } else {
+                vec![]
+            },
+            traps,
+            originals: deps.query_rules.originals(),
+            sites,
+            frontier,
+            ..CrawlDb::from_store(
+                seeds.first().map_or("", |seed| seed.as_str()),
+                &*deps.data_store.read().await,
+            )
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        serde_json::from_slice(&fs::read(path)?).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn seeds(&self) -> Vec<String> {
+        if self.seeds.is_empty() {
+            vec![self.seed.clone()]
+        } else {
+            self.seeds.clone()
+        }
+    }
+
+    // The first seed that could not be requested at all, and why
+    pub fn seed_error(&self) -> Option<(String, String)> {
+        self.seeds().into_iter().find_map(|seed| {
+            let error = self.pages.get(&seed)?.page.as_ref()?.error.clone()?;
+            Some((seed, error))
+        })
+    }
+
+    pub fn visited(&self) -> impl Iterator<Item = (&String, &DataStoreEntry<String>)> {
+        self.pages.iter().filter(|(_, entry)| entry.visited)
+    }
+
+    // URLs that were enqueued but not crawled, e.g. because the budget was used up
+    pub fn pending(&self) -> Vec<String> {
+        self.pages
+            .iter()
+            .filter(|(_, entry)| !entry.visited && entry.referrer.is_some() && entry.page.is_none())
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    pub fn into_store(self) -> Store<String, String> {
+        Store::from_entries(self.pages)
+    }
+}
+
+#[cfg(test)]
+mod crawl_db_tests {
+    use super::CrawlDb;
+    use crate::data_store::{DataStore, Page, Store};
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn crawl_db_round_trips_a_data_store_through_disk() {
+        let path = std::env::temp_dir().join(format!("crawl-{}.json", Uuid::new_v4()));
+        let mut store = Store::new();
+        store.add("/".to_owned(), Some("/a".to_owned()));
+        store.visited(&"/".to_owned());
+        store.discovered("/a".to_owned(), "/".to_owned(), 1);
+
+        let db = CrawlDb::from_store("/", &store);
+        db.save(&path).unwrap();
+        let loaded = CrawlDb::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, db);
+        assert_eq!(loaded.visited().count(), 1);
+        assert_eq!(loaded.pending(), vec!["/a".to_owned()]);
+        assert_eq!(loaded.into_store(), store);
+    }
+
+    #[test]
+    fn crawl_db_finds_seeds_that_could_not_be_requested() {
+        let mut store = Store::new();
+        store.add("https://a.com/".to_owned(), None);
+        store.page(
+            &"https://a.com/".to_owned(),
+            Page {
+                error: Some("connection refused".to_owned()),
+                ..Page::default()
+            },
+        );
+        store.add("https://b.com/".to_owned(), None);
+        store.page(&"https://b.com/".to_owned(), Page::default());
+
+        let unreachable = CrawlDb {
+            seeds: vec!["https://b.com/".to_owned(), "https://a.com/".to_owned()],
+            ..CrawlDb::from_store("https://b.com/", &store)
+        };
+        let reachable = CrawlDb::from_store("https://b.com/", &store);
+
+        assert_eq!(
+            unreachable.seed_error(),
+            Some(("https://a.com/".to_owned(), "connection refused".to_owned()))
+        );
+        assert_eq!(reachable.seed_error(), None);
+    }
+}
@@ -1,32 +1,186 @@
 use crate::{
-    dependencies::{Dependencies, Deps, DepsConcrete},
+    cache::HttpCache,
+    crawl_db::CrawlDb,
+    data_store::{DataStore, DataStoreEntry, Page, Store},
+    dependencies::{
+        bloom_seen_set, crawl_hooks, data_store, priority_frontier, restored_store, site_frontier,
+        spilling_frontier, trap_detector, url_frontier, Archive, Dependencies, DepsConcrete,
+        Frontier, Hooks, Output, UrlFrontierOptions,
+    },
+    events::{CrawlEvent, CrawlEvents, FilterReason},
+    external::verify_external_links,
     fetch::{Fetch, HttpFetch},
+    focus::{FocusScorer, TopicFocus},
+    hooks::CrawlHooks,
+    incremental::{
+        fetch_sitemap, fetch_sitemap_xml, seed_from_checkpoint, seed_from_previous, Sitemap,
+    },
+    output::{PageRecord, PageWriter, ResponseWriter},
     parser::Parser,
-    url::{self, filter_url, process_url, UrlParts},
+    priority_frontier::{
+        BreadthFirst, DepthFirst, FrontierOrder, InlinkCount, KeywordRelevance, SitemapPriority,
+    },
+    record::stable_hash,
+    report::{broken_links, checked_links, site_summaries, LinkCheck, SiteSummary},
+    site_frontier::{Site, SiteOptions},
+    trap::{Trap, TrapOptions},
+    url::{
+        self, filter_url_in, process_url, scope, site_scope, url_kind, QueryRules, Scope, UrlKind,
+    },
 };
+use ::url::Url;
 use log::{info, warn};
-use reqwest::IntoUrl;
-use std::{fmt::Display, hash::Hash, io::Error, sync::Arc};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+    mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{
+        mpsc::{channel, Sender},
+        RwLock,
+    },
+    task::JoinSet,
+};
 
-pub async fn crawl_seed<T: Clone + Hash + Eq + IntoUrl + Send + Display + AsRef<str>, U>(
-    deps: Dependencies<T, U>,
-    http: HttpFetch,
-    original_url_parts: Arc<Result<UrlParts, url::Error>>,
+pub async fn crawl_seed<F: Fetch + Send + Sync + 'static>(
+    deps: DepsConcrete,
+    http: F,
+    scopes: Arc<[Scope]>,
 ) -> Result<(), Error> {
-    let task = tokio::spawn(crawl(deps, http, original_url_parts.clone()));
+    let task = tokio::spawn(crawl(deps, http, scopes));
     task.await?;
     Ok(())
 }
 
-pub async fn crawl<T: Clone + Hash + Eq + IntoUrl + Send + Display + AsRef<str>, U>(
-    deps: Dependencies<T, U>,
-    http: HttpFetch,
-    original_url_parts: Arc<Result<UrlParts, url::Error>>,
+// The seed is crawled on its own first, then `workers` tasks share the frontier
+pub async fn crawl_with_workers<F: Fetch + Clone + Send + Sync + 'static>(
+    deps: DepsConcrete,
+    http: F,
+    scopes: Arc<[Scope]>,
+    workers: u8,
+) -> Result<(), Error> {
+    crawl_seed(deps.clone(), http.clone(), scopes.clone()).await?;
+
+    let mut tasks = JoinSet::new();
+    for _n in 0..workers {
+        // each worker gets its own client
+        tasks.spawn(crawl(deps.clone(), http.clone(), scopes.clone()));
+    }
+
+    while let Some(_res) = tasks.join_next().await {
+        info!("Worker completed");
+    }
+
+    hook(&deps, |hooks| hooks.on_finish()).await;
+    Ok(())
+}
+
+// Without a seen-set every link to a page that was not visited yet is enqueued
+async fn first_seen(deps: &DepsConcrete, url: &str) -> bool {
+    match &deps.seen {
+        Some(seen) => seen.write().await.insert(url),
+        None => true,
+    }
+}
+
+// A seen-set is the only record of the links that were enqueued already, the data store
+// only knows about the pages that were visited
+async fn known(
+    deps: &DepsConcrete,
+    data_store: &(dyn DataStore<String, String> + Send + Sync),
+    url: &str,
+) -> bool {
+    match &deps.seen {
+        Some(seen) => seen.read().await.contains(url),
+        None => data_store.has_visited(&url.to_owned()),
+    }
+}
+
+async fn trap(deps: &DepsConcrete, url: &str, source: &str) -> Option<Trap> {
+    let mut traps = deps.traps.as_ref()?.write().await;
+    if traps.check(url, source) {
+        return None;
+    }
+
+    traps
+        .trips()
+        .iter()
+        .rev()
+        .find(|trip| trip.url == url)
+        .map(|trip| trip.trap.clone())
+}
+
+async fn hook(deps: &DepsConcrete, call: impl FnOnce(&mut (dyn CrawlHooks + Send + Sync))) {
+    if let Some(hooks) = &deps.hooks {
+        call(&mut *hooks.write().await);
+    }
+}
+
+// Waits for room in the channel, so a slow consumer slows the crawl down
+async fn emit(deps: &DepsConcrete, event: impl FnOnce() -> CrawlEvent) {
+    if let Some(events) = &deps.events {
+        // Only fails once the stream was dropped, the crawl loop stops before the next URL
+        let _ = events.send(event()).await;
+    }
+}
+
+async fn filtered(deps: &DepsConcrete, source: &str, url: &str, reason: FilterReason) {
+    emit(deps, || CrawlEvent::UrlFiltered {
+        source: source.to_owned(),
+        url: url.to_owned(),
+        reason,
+    })
+    .await;
+}
+
+async fn error(deps: &DepsConcrete, url: &str, error: String) {
+    hook(deps, |hooks| hooks.on_error(url, &error)).await;
+    emit(deps, || CrawlEvent::Error {
+        url: Some(url.to_owned()),
+        error,
+    })
+    .await;
+}
+
+// Links that can't be parsed are never fetched, the parse error is stored in place of a
+// response so they show up as broken links of the page they were found on
+fn invalid_url(
+    data_store: &mut (dyn DataStore<String, String> + Send + Sync),
+    source: &str,
+    url: &str,
+    error: url::Error,
 ) {
+    warn!("Invalid URL {} found on {} - {}", url, source, error);
+
+    data_store.kind(url.to_owned(), UrlKind::Malformed);
+    data_store.page(
+        &url.to_owned(),
+        Page {
+            error: Some(error.to_string()),
+            ..Page::default()
+        },
+    );
+}
+
+pub async fn crawl<F: Fetch>(deps: DepsConcrete, http: F, scopes: Arc<[Scope]>) {
     let mut url_frontier_write = deps.url_frontier.write().await;
     let mut data_store = deps.data_store.write().await;
 
     loop {
+        // A stream that was dropped stops the crawl, tasks spawned for it keep running otherwise
+        if deps
+            .events
+            .as_ref()
+            .is_some_and(|events| events.is_closed())
+        {
+            info!("Event stream dropped, stopping");
+            return;
+        }
+
         let Some(current_url) = url_frontier_write.dequeue().await else {
             return;
         };
@@ -34,56 +188,829 @@ pub async fn crawl<T: Clone + Hash + Eq + IntoUrl + Send + Display + AsRef<str>,
         if data_store.has_visited(&current_url) {
             continue;
         }
+        // Seeds reach the frontier without going through the seen-set
+        first_seen(&deps, &current_url).await;
 
-        let content = http.get(current_url.clone()).await;
-        if content.is_err() {
-            warn!(
-                "Error requesting URL {} - {:?}",
-                &current_url,
-                content.err()
-            );
-            continue;
+        hook(&deps, |hooks| hooks.on_request(&current_url)).await;
+        let response = match http.get(current_url.clone()).await {
+            Ok(response) => {
+                hook(&deps, |hooks| hooks.on_response(&response)).await;
+                response
+            }
+            Err(e) => {
+                warn!("Error requesting URL {} - {:?}", &current_url, e);
+                // Kept as a page of its own, so that links to it show up as broken
+                data_store.add(current_url.clone(), None);
+                data_store.visited(&current_url);
+                data_store.page(
+                    &current_url,
+                    Page {
+                        error: Some(e.to_string()),
+                        ..Page::default()
+                    },
+                );
+                error(&deps, &current_url, e.to_string()).await;
+                continue;
+            }
+        };
+
+        // A 304 carries the cached response, which an earlier crawl has archived already
+        if let Some(archive) = deps.archive.as_ref().filter(|_| !response.unchanged) {
+            if let Err(e) = archive.write().await.write(&response) {
+                warn!("Error archiving URL {} - {:?}", &current_url, e);
+                error(&deps, &current_url, e.to_string()).await;
+            }
+        }
+
+        // Parser is not Send so it has to be dropped before the next await point
+        let (urls_founds, metadata, text) = {
+            let parser = Parser::new(response.text());
+            let text = deps.focus.as_ref().map(|_| parser.text());
+            (parser.all_links(), parser.metadata(), text)
         };
-        let content = content.unwrap();
+        let relevance = deps
+            .focus
+            .as_ref()
+            .zip(text)
+            .map(|(focus, text)| focus.relevance(&text));
 
         data_store.add(current_url.clone(), None);
         data_store.visited(&current_url);
+        data_store.page(
+            &current_url,
+            Page {
+                status: response.status,
+                content_type: response.content_type().map(|value| value.to_owned()),
+                content_hash: stable_hash(&response.body),
+                metadata: metadata.clone(),
+                error: None,
+                relevance,
+                unchanged: response.unchanged,
+            },
+        );
+
+        let (depth, referrer) = data_store
+            .get(&current_url)
+            .map(|entry| (entry.depth, entry.referrer.clone()))
+            .unwrap_or_default();
 
-        let urls_founds = Parser::new(content).all_links();
+        if response.unchanged {
+            info!("Visited URL (unchanged): {}", current_url);
+        } else {
+            info!("Visited URL: {}", current_url);
+        }
+        if let Some(relevance) = relevance {
+            info!("Relevance: {:.2}", relevance);
+        }
 
-        info!("Visited URL: {}", current_url);
+        let mut outlinks = vec![];
+        // Links are relative to where the page ended up, e.g. after a redirect to /about/
+        let base = if response.url.is_empty() {
+            &current_url
+        } else {
+            &response.url
+        };
 
-        for url in urls_founds {
-            let url = process_url(url, &current_url);
+        for href in urls_founds {
+            let url = match process_url(href.clone(), base, &deps.query_rules) {
+                Ok(url) => url,
+                Err(e) => {
+                    let reason = FilterReason::Invalid(e.to_string());
+                    data_store.add(current_url.clone(), Some(href.clone()));
+                    invalid_url(&mut *data_store, &current_url, &href, e);
+                    filtered(&deps, &current_url, &href, reason).await;
+                    outlinks.push(href);
+                    continue;
+                }
+            };
             info!("Found URL: {}", url);
 
             data_store.add(current_url.clone(), Some(url.clone()));
+            // With a seen-set only enqueued links get an entry, which keeps large crawls
+            // compact. The report tells the kind of the others from their URL.
+            if deps.seen.is_none() {
+                data_store.kind(url.clone(), url_kind(&url));
+            }
+            outlinks.push(url.clone());
 
-            if let Some(url) = filter_url(url, original_url_parts.clone()) {
-                if !data_store.has_visited(&url) {
-                    url_frontier_write.enqueue(url);
+            // Every site is crawled within its own scope, even if it links to another seed
+            let url = match filter_url_in(url.clone(), site_scope(&current_url, &scopes)) {
+                Ok(Some(url)) => url,
+                Ok(None) => {
+                    let reason = match url_kind(&url) {
+                        kind if !kind.is_fetchable() => FilterReason::NotFetchable(kind),
+                        _ => FilterReason::OutOfScope,
+                    };
+                    filtered(&deps, &current_url, &url, reason).await;
+                    continue;
+                }
+                Err(e) => {
+                    let reason = FilterReason::Invalid(e.to_string());
+                    invalid_url(&mut *data_store, &current_url, &url, e);
+                    filtered(&deps, &current_url, &url, reason).await;
+                    continue;
                 }
             };
+
+            let followed = match (&deps.focus, relevance) {
+                (Some(focus), Some(relevance)) => focus.follow(&current_url, relevance, &url),
+                _ => true,
+            };
+            if !followed {
+                filtered(&deps, &current_url, &url, FilterReason::Irrelevant).await;
+                continue;
+            }
+
+            if known(&deps, &*data_store, &url).await {
+                continue;
+            }
+            if let Some(trap) = trap(&deps, &url, &current_url).await {
+                filtered(&deps, &current_url, &url, FilterReason::Trap(trap)).await;
+                continue;
+            }
+
+            if first_seen(&deps, &url).await {
+                data_store.discovered(url.clone(), current_url.clone(), depth + 1);
+                url_frontier_write.enqueue(url.clone());
+                emit(&deps, || CrawlEvent::LinkDiscovered {
+                    source: current_url.clone(),
+                    url,
+                    depth: depth + 1,
+                })
+                .await;
+            }
         }
 
+        hook(&deps, |hooks| hooks.on_links(&current_url, &outlinks)).await;
+
+        let record = PageRecord {
+            url: current_url.clone(),
+            status: response.status,
+            content_type: response.content_type().map(|value| value.to_owned()),
+            size: response.size(),
+            elapsed_ms: response.elapsed.as_millis(),
+            unchanged: response.unchanged,
+            depth,
+            referrer,
+            outlinks,
+            metadata,
+            relevance,
+            original: deps.query_rules.original(&current_url),
+        };
+
+        if let Some(output) = &deps.output {
+            if let Err(e) = output.write().await.write(&record) {
+                warn!("Error writing output for URL {} - {:?}", &current_url, e);
+                error(&deps, &current_url, e.to_string()).await;
+            }
+        }
+        emit(&deps, || CrawlEvent::PageFetched(record)).await;
+
         info!("--------------------------------------------");
     }
 }
 
+// Set through the CrawlerBuilder, more options may be added
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CrawlerOptions {
+    pub workers: u8,
+    // Politeness delay between two requests to the same site
+    pub delay: Duration,
+    // Pages requested from each site at most
+    pub max_pages: Option<usize>,
+    pub query_rules: QueryRules,
+    // Off by default, TrapOptions::recommended() are the limits of --traps
+    pub traps: TrapOptions,
+    // Verify links to other sites with one request each over the network, without crawling them
+    pub external_links: bool,
+    // Events a stream holds before the crawl waits for the consumer
+    pub events_buffer: usize,
+    // Order in which the frontier of each site hands out its URLs
+    pub frontier: FrontierOrder,
+    // Terms of FrontierOrder::Keywords
+    pub keywords: Vec<String>,
+    // The fifo frontier spills to segment files in this directory past frontier_buffer URLs
+    pub spill_dir: Option<PathBuf>,
+    pub frontier_buffer: usize,
+    // False positive rate of a Bloom filter remembering enqueued URLs, and the number of URLs
+    // it holds before it grows
+    pub bloom: Option<f64>,
+    pub bloom_capacity: usize,
+}
+
+// The defaults of the command line, which takes e.g. its --workers-n and --delay from here
+impl Default for CrawlerOptions {
+    fn default() -> Self {
+        CrawlerOptions {
+            workers: 1,
+            delay: Duration::from_secs(2),
+            max_pages: None,
+            query_rules: QueryRules::default(),
+            traps: TrapOptions::default(),
+            external_links: false,
+            events_buffer: 64,
+            frontier: FrontierOrder::Fifo,
+            keywords: vec![],
+            spill_dir: None,
+            frontier_buffer: 100_000,
+            bloom: None,
+            bloom_capacity: 1_000_000,
+        }
+    }
+}
+
+// A crawl of one or more sites, each seed within its own scope, for embedding the crate:
+//
+//     let report = Crawler::builder()
+//         .seed("https://example.com/")
+//         .max_pages(100)
+//         .build()?
+//         .run()
+//         .await?;
+pub struct Crawler<F = HttpFetch> {
+    seeds: Vec<String>,
+    options: CrawlerOptions,
+    fetch: F,
+    output: Option<Output>,
+    archive: Option<Archive>,
+    hooks: Option<Hooks>,
+    focus: Option<Arc<TopicFocus>>,
+    // Options of the seeds that don't go by the ones for every site
+    sites: HashMap<String, SiteOptions>,
+    // The crawl an incremental crawl compares with, and the one a resumed crawl carries on
+    since: Option<CrawlDb>,
+    resumed: Option<CrawlDb>,
+    cache: Option<String>,
+    db: Option<PathBuf>,
+}
+
+pub struct CrawlerBuilder<F = HttpFetch> {
+    seeds: Vec<String>,
+    options: CrawlerOptions,
+    fetch: F,
+    output: Option<Output>,
+    archive: Option<Archive>,
+    hooks: Option<Hooks>,
+    focus: Option<Arc<TopicFocus>>,
+    sites: HashMap<String, SiteOptions>,
+    since: Option<CrawlDb>,
+    resumed: Option<CrawlDb>,
+    cache: Option<String>,
+    db: Option<PathBuf>,
+}
+
+impl Crawler {
+    pub fn builder() -> CrawlerBuilder {
+        CrawlerBuilder {
+            seeds: vec![],
+            options: CrawlerOptions::default(),
+            fetch: HttpFetch::new(),
+            output: None,
+            archive: None,
+            hooks: None,
+            focus: None,
+            sites: HashMap::new(),
+            since: None,
+            resumed: None,
+            cache: None,
+            db: None,
+        }
+    }
+}
+
+impl<F: Fetch + Clone + Send + Sync + 'static> CrawlerBuilder<F> {
+    pub fn seed(self, seed: impl Into<String>) -> CrawlerBuilder<F> {
+        let mut seeds = self.seeds;
+        seeds.push(seed.into());
+        CrawlerBuilder { seeds, ..self }
+    }
+
+    pub fn seeds(self, seeds: impl IntoIterator<Item = impl Into<String>>) -> CrawlerBuilder<F> {
+        let mut all_seeds = self.seeds;
+        all_seeds.extend(seeds.into_iter().map(Into::into));
+        CrawlerBuilder {
+            seeds: all_seeds,
+            ..self
+        }
+    }
+
+    pub fn options(self, options: CrawlerOptions) -> CrawlerBuilder<F> {
+        CrawlerBuilder { options, ..self }
+    }
+
+    pub fn workers(self, workers: u8) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                workers,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn delay(self, delay: Duration) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                delay,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn max_pages(self, max_pages: usize) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                max_pages: Some(max_pages),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn query_rules(self, query_rules: QueryRules) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                query_rules,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn traps(self, traps: TrapOptions) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                traps,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn external_links(self, external_links: bool) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                external_links,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn events_buffer(self, events_buffer: usize) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                events_buffer,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn frontier(self, frontier: FrontierOrder) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                frontier,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn keywords(
+        self,
+        keywords: impl IntoIterator<Item = impl Into<String>>,
+    ) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                keywords: keywords.into_iter().map(Into::into).collect(),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    // Only the fifo frontier spills, to a directory of its own for every site of a
+    // multi-site crawl
+    pub fn spill_dir(self, spill_dir: impl Into<PathBuf>, buffer: usize) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                spill_dir: Some(spill_dir.into()),
+                frontier_buffer: buffer,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    pub fn bloom(self, fp_rate: f64, capacity: usize) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            options: CrawlerOptions {
+                bloom: Some(fp_rate),
+                bloom_capacity: capacity,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    // Orders the frontier of every site by the relevance of pages to a topic, whatever the
+    // frontier order
+    pub fn focus(self, focus: TopicFocus) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            focus: Some(Arc::new(focus)),
+            ..self
+        }
+    }
+
+    // Makes `seed` a site with a frontier of its own, e.g. with a delay of its own
+    pub fn site(self, seed: impl Into<String>, options: SiteOptions) -> CrawlerBuilder<F> {
+        let mut sites = self.sites;
+        sites.insert(seed.into(), options);
+        CrawlerBuilder { sites, ..self }
+    }
+
+    // Crawls incrementally, from the pages of `previous` that changed since
+    pub fn since(self, previous: CrawlDb) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            since: Some(previous),
+            ..self
+        }
+    }
+
+    // Carries on with a crawl, requesting the URLs it found but did not get to
+    pub fn resume(self, checkpoint: CrawlDb) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            resumed: Some(checkpoint),
+            ..self
+        }
+    }
+
+    // Saves the db of the report to `path` once the crawl is done
+    pub fn db(self, path: impl Into<PathBuf>) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            db: Some(path.into()),
+            ..self
+        }
+    }
+
+    // Fetches over the network through a cache in `dir`, which the db records for later
+    // crawls to revalidate with
+    pub fn cache(self, dir: impl Into<String>) -> Result<CrawlerBuilder<HttpCache>, Error> {
+        let dir = dir.into();
+        let fetch = HttpCache::with_dir(HttpFetch::new(), &dir)?;
+        Ok(CrawlerBuilder {
+            cache: Some(dir),
+            ..self.fetch(fetch)
+        })
+    }
+
+    pub fn output(self, output: impl PageWriter + Send + Sync + 'static) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            output: Some(Output(Arc::new(RwLock::new(output)))),
+            ..self
+        }
+    }
+
+    pub fn archive(
+        self,
+        archive: impl ResponseWriter + Send + Sync + 'static,
+    ) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            archive: Some(Archive(Arc::new(RwLock::new(archive)))),
+            ..self
+        }
+    }
+
+    pub fn hooks(self, hooks: impl CrawlHooks + Send + Sync + 'static) -> CrawlerBuilder<F> {
+        CrawlerBuilder {
+            hooks: Some(crawl_hooks(hooks)),
+            ..self
+        }
+    }
+
+    // Fetches pages with e.g. a FileFetch, a ReplayFetch or an HttpCache instead
+    pub fn fetch<G: Fetch + Clone + Send + Sync + 'static>(self, fetch: G) -> CrawlerBuilder<G> {
+        CrawlerBuilder {
+            seeds: self.seeds,
+            options: self.options,
+            fetch,
+            output: self.output,
+            archive: self.archive,
+            hooks: self.hooks,
+            focus: self.focus,
+            sites: self.sites,
+            since: self.since,
+            resumed: self.resumed,
+            cache: self.cache,
+            db: self.db,
+        }
+    }
+
+    pub fn build(self) -> Result<Crawler<F>, Error> {
+        if self.seeds.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a crawl needs at least one seed",
+            ));
+        }
+
+        if self.options.events_buffer == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the events buffer must hold at least one event",
+            ));
+        }
+
+        let mut seeds: Vec<String> = vec![];
+        for seed in self.seeds {
+            if let Err(e) = Url::parse(&seed) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid seed {}: {}", seed, e),
+                ));
+            }
+            // Seeds follow the query rules like every link found
+            let seed = self.options.query_rules.apply(seed);
+            if !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+        }
+        let sites = self
+            .sites
+            .into_iter()
+            .map(|(seed, options)| (self.options.query_rules.apply(seed), options))
+            .collect();
+
+        Ok(Crawler {
+            seeds,
+            options: self.options,
+            fetch: self.fetch,
+            output: self.output,
+            archive: self.archive,
+            hooks: self.hooks,
+            focus: self.focus,
+            sites,
+            since: self.since,
+            resumed: self.resumed,
+            cache: self.cache,
+            db: self.db,
+        })
+    }
+}
+
+impl<F: Fetch + Clone + Send + Sync + 'static> Crawler<F> {
+    pub fn seeds(&self) -> &[String] {
+        &self.seeds
+    }
+
+    pub fn options(&self) -> &CrawlerOptions {
+        &self.options
+    }
+
+    // The frontier of a site, in the order of the crawl
+    async fn frontier(
+        &self,
+        seed: &str,
+        delay_s: Option<u64>,
+        spill_dir: Option<PathBuf>,
+    ) -> Result<Frontier<String>, Error> {
+        let options = UrlFrontierOptions {
+            delay_s,
+            uri: seed.to_owned(),
+        };
+        let url_frontier = match (&self.options.frontier, &self.focus) {
+            (_, Some(focus)) => priority_frontier(options, FocusScorer(focus.clone())),
+            (FrontierOrder::Fifo | FrontierOrder::Focused, _) => match spill_dir {
+                Some(dir) => spilling_frontier(options, dir, self.options.frontier_buffer)?,
+                None => url_frontier(options),
+            },
+            (FrontierOrder::Bfs, _) => priority_frontier(options, BreadthFirst),
+            (FrontierOrder::Dfs, _) => priority_frontier(options, DepthFirst),
+            (FrontierOrder::Inlinks, _) => priority_frontier(options, InlinkCount),
+            (FrontierOrder::Keywords, _) => {
+                priority_frontier(options, KeywordRelevance::new(&self.options.keywords))
+            }
+            (FrontierOrder::Sitemap, _) => {
+                let sitemap = fetch_sitemap_xml(&self.fetch, seed).await;
+                priority_frontier(
+                    options,
+                    SitemapPriority::from_sitemap(sitemap.unwrap_or_default()),
+                )
+            }
+        };
+
+        Ok(url_frontier)
+    }
+
+    // Several seeds, a page budget or options of a site make every seed a site with its own
+    // frontier, politeness delay and page budget, a single seed is crawled from one frontier
+    async fn dependencies(
+        &mut self,
+        events: Option<Sender<CrawlEvent>>,
+    ) -> Result<DepsConcrete, Error> {
+        // A resumed crawl carries on with the pages it already has
+        let mut deps = match &self.resumed {
+            Some(resumed) => Dependencies::new()
+                .data_store(restored_store(Store::from_entries(resumed.pages.clone()))),
+            None => Dependencies::new().data_store(data_store()),
+        };
+
+        if self.seeds.len() > 1 || self.options.max_pages.is_some() || !self.sites.is_empty() {
+            let mut sites = vec![];
+            for (n, seed) in self.seeds.iter().enumerate() {
+                let spill_dir = self
+                    .options
+                    .spill_dir
+                    .as_ref()
+                    .map(|dir| dir.join(format!("site-{}", n)));
+                let options = self.sites.get(seed).cloned().unwrap_or(SiteOptions {
+                    delay: self.options.delay,
+                    max_pages: self.options.max_pages,
+                });
+
+                match self.frontier(seed, None, spill_dir).await {
+                    Ok(queue) => sites.push(Site::new(seed, queue, options)),
+                    Err(e) => {
+                        return Err(Error::new(
+                            e.kind(),
+                            format!("unable to create the frontier of {}: {}", seed, e),
+                        ))
+                    }
+                }
+            }
+            deps = deps.sites(site_frontier(sites));
+        } else {
+            let delay_s = Some(self.options.delay.as_secs());
+            let spill_dir = self.options.spill_dir.clone();
+            match self.frontier(&self.seeds[0], delay_s, spill_dir).await {
+                Ok(queue) => deps = deps.url_frontier(queue),
+                Err(e) => {
+                    return Err(Error::new(
+                        e.kind(),
+                        format!("unable to create the frontier: {}", e),
+                    ))
+                }
+            }
+        }
+
+        deps = deps.query_rules(mem::take(&mut self.options.query_rules));
+        if let Some(focus) = &self.focus {
+            deps = deps.focus(focus.clone());
+        }
+        if self.options.traps.is_enabled() {
+            deps = deps.traps(trap_detector(self.options.traps.clone()));
+        }
+        if let Some(fp_rate) = self.options.bloom {
+            deps = deps.seen(bloom_seen_set(self.options.bloom_capacity, fp_rate));
+        }
+        if let Some(output) = self.output.take() {
+            deps = deps.output(output);
+        }
+        if let Some(archive) = self.archive.take() {
+            deps = deps.archive(archive);
+        }
+        if let Some(hooks) = self.hooks.take() {
+            deps = deps.hooks(hooks);
+        }
+        if let Some(events) = events {
+            deps = deps.events(events);
+        }
+
+        Ok(deps.build())
+    }
+
+    pub async fn run(self) -> Result<CrawlReport, Error> {
+        self.crawl(None).await
+    }
+
+    // Runs the crawl on a task of its own, which has to be spawned within a tokio runtime
+    pub fn stream(self) -> CrawlEvents {
+        let (sender, receiver) = channel(self.options.events_buffer);
+
+        let task = tokio::spawn(async move {
+            let finished = match self.crawl(Some(sender.clone())).await {
+                Ok(report) => CrawlEvent::Finished(report),
+                Err(e) => CrawlEvent::Error {
+                    url: None,
+                    error: e.to_string(),
+                },
+            };
+            let _ = sender.send(finished).await;
+        });
+
+        CrawlEvents::new(receiver, task)
+    }
+
+    async fn crawl(mut self, events: Option<Sender<CrawlEvent>>) -> Result<CrawlReport, Error> {
+        let start = Instant::now();
+        let deps = self.dependencies(events).await?;
+        let scopes: Arc<[Scope]> = self.seeds.iter().map(|seed| scope(seed)).collect();
+
+        // The sitemap is kept for incremental crawls to come
+        let mut sitemap = Sitemap::new();
+        if self.since.is_some() || self.db.is_some() {
+            for seed in &self.seeds {
+                sitemap.extend(fetch_sitemap(&self.fetch, seed).await);
+            }
+        }
+
+        if let Some(previous) = &self.since {
+            seed_from_previous(&deps, previous, &sitemap, &scopes).await;
+        }
+        if let Some(resumed) = &self.resumed {
+            seed_from_checkpoint(&deps, resumed).await;
+        }
+
+        crawl_with_workers(
+            deps.clone(),
+            self.fetch.clone(),
+            scopes.clone(),
+            self.options.workers,
+        )
+        .await?;
+
+        // External links are on the web even when the site is served from elsewhere
+        if self.options.external_links {
+            verify_external_links(&deps, &HttpFetch::new(), scopes, self.options.delay).await;
+        }
+
+        let mut db = CrawlDb {
+            sitemap,
+            cache: self.cache.take(),
+            ..CrawlDb::from_dependencies(&self.seeds, &deps).await
+        };
+
+        // A resumed crawl keeps what the crawl it carries on from found
+        if let Some(resumed) = self.resumed.take() {
+            db.traps.splice(0..0, resumed.traps);
+            for (url, lastmod) in resumed.sitemap {
+                db.sitemap.entry(url).or_insert(lastmod);
+            }
+            for (url, original) in resumed.originals {
+                db.originals.entry(url).or_insert(original);
+            }
+        }
+
+        if let Some(path) = &self.db {
+            db.save(path)?;
+        }
+
+        Ok(CrawlReport {
+            db,
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+// What a Crawler found, the db can be saved to be used by the command line later on
+#[derive(Debug, PartialEq)]
+pub struct CrawlReport {
+    pub db: CrawlDb,
+    pub elapsed: Duration,
+}
+
+impl CrawlReport {
+    pub fn pages(&self) -> impl Iterator<Item = (&String, &DataStoreEntry<String>)> {
+        self.db.visited()
+    }
+
+    pub fn links(&self) -> Vec<LinkCheck> {
+        checked_links(&self.db)
+    }
+
+    pub fn broken_links(&self) -> Vec<LinkCheck> {
+        broken_links(&self.db)
+    }
+
+    pub fn sites(&self) -> Vec<SiteSummary> {
+        site_summaries(&self.db)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.db.save(path)
+    }
+}
+
 #[cfg(test)]
 mod task_tests {
     use crate::crawler::crawl;
-    use crate::data_store::{DataStore, DataStoreEntry};
-    use crate::dependencies::{Dependencies, Frontier, MemoryStore};
-    use crate::fetch::{Fetch, HttpFetch};
-    use crate::url::url_parts;
+    use crate::data_store::{DataStore, DataStoreEntry, Page};
+    use crate::dependencies::{
+        bloom_seen_set, data_store, url_frontier, Dependencies, Frontier, MemoryStore,
+        UrlFrontierOptions,
+    };
+    use crate::fetch::Response;
+    use crate::replay::ReplayFetch;
+    use crate::report::url_inventory;
+    use crate::url::{scope, UrlKind};
     use crate::url_frontier::{Dequeue, Enqueue, Queue};
     use async_trait::async_trait;
     use mockall::{mock, predicate, Sequence};
-    use std::sync::Arc;
+    use std::{collections::BTreeSet, sync::Arc};
     use tokio::sync::RwLock;
-    use wiremock::matchers::{method, path};
-    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn make_hrefs(base_uri: &str) -> Vec<String> {
         let url1 = format!("{}/about", &base_uri);
@@ -100,16 +1027,29 @@ mod task_tests {
             .join("")
     }
 
+    fn response(url: &str, body: String) -> Response {
+        Response {
+            url: url.to_owned(),
+            status: 200,
+            body: body.into_bytes(),
+            ..Response::default()
+        }
+    }
+
     mock!(
         #[derive(Debug)]
         Store<T, U: 'static> {}
 
         impl<T, U: 'static> DataStore<T, U> for Store<T, U> {
             fn add(&mut self, key: T, value: Option<U>);
+            fn discovered(&mut self, key: T, referrer: U, depth: usize);
             fn visited(&mut self, key: &T);
+            fn page(&mut self, key: &T, page: Page);
+            fn kind(&mut self, key: T, kind: UrlKind);
             fn has_visited(&self, key: &T) -> bool;
             fn exists(&self, key: &T) -> bool;
             fn get<'a>(&'a self, key: &T) -> Option<&'a DataStoreEntry<U>>;
+            fn keys(&self) -> Vec<T>;
         }
     );
 
@@ -130,45 +1070,18 @@ mod task_tests {
 
     #[tokio::test]
     async fn task_runs_correctly() {
-        let listener = std::net::TcpListener::bind("localhost:45367").unwrap();
-        let mock_server = MockServer::builder().listener(listener).start().await;
-
-        let port = "45367";
-        let main_url = format!("http://localhost:{}", port);
+        let main_url = "https://example.com".to_owned();
         let hrefs = make_hrefs(&main_url);
         let about_url = hrefs.first().unwrap();
         let contact_url = hrefs.get(1).unwrap();
         let anchors = make_anchors(hrefs.to_vec());
 
-        // First request (/)
-        let response = ResponseTemplate::new(200).set_body_string(anchors);
-        Mock::given(method("GET"))
-            .and(path("/"))
-            .respond_with(response.clone())
-            .up_to_n_times(1)
-            .expect(1..)
-            .mount(&mock_server)
-            .await;
-
-        // Second request (/about)
-        let response = ResponseTemplate::new(200).set_body_string("".to_owned());
-        Mock::given(method("GET"))
-            .and(path("/about"))
-            .respond_with(response.clone())
-            .up_to_n_times(1)
-            .expect(1..)
-            .mount(&mock_server)
-            .await;
-
-        // Third request (/contact)
-        let response = ResponseTemplate::new(200).set_body_string("".to_owned());
-        Mock::given(method("GET"))
-            .and(path("/contact"))
-            .respond_with(response.clone())
-            .up_to_n_times(1)
-            .expect(1..)
-            .mount(&mock_server)
-            .await;
+        // Recorded responses for /, /about and /contact
+        let client = ReplayFetch::from_responses(vec![
+            response(&main_url, anchors),
+            response(about_url, "".to_owned()),
+            response(contact_url, "".to_owned()),
+        ]);
 
         let mut url_frontier_mock = MockURLFrontier::new();
         let mut data_store_mock = MockStore::new();
@@ -202,6 +1115,21 @@ mod task_tests {
             .returning(|_| {})
             .in_sequence(&mut sequence);
 
+        let key = main_url.clone();
+        data_store_mock
+            .expect_page()
+            .once()
+            .withf(move |k, page| k == &key && page.status == 200)
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
+        data_store_mock
+            .expect_get()
+            .once()
+            .with(predicate::eq(main_url.clone()))
+            .return_const(None)
+            .in_sequence(&mut sequence);
+
         data_store_mock
             .expect_add()
             .once()
@@ -212,6 +1140,16 @@ mod task_tests {
             .returning(|_, _| {})
             .in_sequence(&mut sequence);
 
+        data_store_mock
+            .expect_kind()
+            .once()
+            .with(
+                predicate::eq(about_url.clone()),
+                predicate::eq(UrlKind::Page),
+            )
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
         data_store_mock
             .expect_has_visited()
             .once()
@@ -219,6 +1157,17 @@ mod task_tests {
             .returning(|_| false)
             .in_sequence(&mut sequence);
 
+        data_store_mock
+            .expect_discovered()
+            .once()
+            .with(
+                predicate::eq(about_url.clone()),
+                predicate::eq(main_url.clone()),
+                predicate::eq(1),
+            )
+            .returning(|_, _, _| {})
+            .in_sequence(&mut sequence);
+
         url_frontier_mock
             .expect_enqueue()
             .with(predicate::eq(about_url.clone()))
@@ -236,6 +1185,16 @@ mod task_tests {
             .returning(|_, _| {})
             .in_sequence(&mut sequence);
 
+        data_store_mock
+            .expect_kind()
+            .once()
+            .with(
+                predicate::eq(contact_url.clone()),
+                predicate::eq(UrlKind::Page),
+            )
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
         data_store_mock
             .expect_has_visited()
             .once()
@@ -243,6 +1202,17 @@ mod task_tests {
             .returning(|_| false)
             .in_sequence(&mut sequence);
 
+        data_store_mock
+            .expect_discovered()
+            .once()
+            .with(
+                predicate::eq(contact_url.clone()),
+                predicate::eq(main_url.clone()),
+                predicate::eq(1),
+            )
+            .returning(|_, _, _| {})
+            .in_sequence(&mut sequence);
+
         url_frontier_mock
             .expect_enqueue()
             .with(predicate::eq(contact_url.clone()))
@@ -260,6 +1230,16 @@ mod task_tests {
             .returning(|_, _| {})
             .in_sequence(&mut sequence);
 
+        data_store_mock
+            .expect_kind()
+            .once()
+            .with(
+                predicate::eq("http://google.com".to_owned()),
+                predicate::eq(UrlKind::Page),
+            )
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
         url_frontier_mock
             .expect_enqueue()
             .with(predicate::eq("http://google.com".to_owned()))
@@ -294,6 +1274,21 @@ mod task_tests {
             .returning(|_| {})
             .in_sequence(&mut sequence);
 
+        let key = about_url.clone();
+        data_store_mock
+            .expect_page()
+            .once()
+            .withf(move |k, page| k == &key && page.status == 200)
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
+        data_store_mock
+            .expect_get()
+            .once()
+            .with(predicate::eq(about_url.clone()))
+            .return_const(None)
+            .in_sequence(&mut sequence);
+
         // /contact
         url_frontier_mock
             .expect_dequeue()
@@ -322,14 +1317,28 @@ mod task_tests {
             .returning(|_| {})
             .in_sequence(&mut sequence);
 
+        let key = contact_url.clone();
+        data_store_mock
+            .expect_page()
+            .once()
+            .withf(move |k, page| k == &key && page.status == 200)
+            .returning(|_, _| {})
+            .in_sequence(&mut sequence);
+
+        data_store_mock
+            .expect_get()
+            .once()
+            .with(predicate::eq(contact_url.clone()))
+            .return_const(None)
+            .in_sequence(&mut sequence);
+
         url_frontier_mock
             .expect_dequeue()
             .times(1)
             .returning(|| None)
             .in_sequence(&mut sequence);
 
-        let client: HttpFetch = Fetch::new();
-        let url_parts = Arc::new(url_parts(&main_url));
+        let scopes = Arc::from([scope(&main_url)]);
         let url_frontier = Arc::new(RwLock::new(url_frontier_mock));
         let data_store = Arc::new(RwLock::new(data_store_mock));
 
@@ -338,6 +1347,464 @@ mod task_tests {
             .data_store(MemoryStore(data_store))
             .build();
 
-        crawl(deps, client, url_parts).await;
+        crawl(deps, client, scopes).await;
+    }
+
+    #[tokio::test]
+    async fn crawl_with_a_seen_set_keeps_entries_for_enqueued_links_only() {
+        let client = ReplayFetch::from_responses([
+            response(
+                "https://a.com/",
+                make_anchors(vec![
+                    "/b".to_owned(),
+                    "/b".to_owned(),
+                    "https://other.com/".to_owned(),
+                    "mailto:me@a.com".to_owned(),
+                ]),
+            ),
+            response("https://a.com/b", make_anchors(vec!["/".to_owned()])),
+        ]);
+        let deps = Dependencies::new()
+            .url_frontier(url_frontier(UrlFrontierOptions {
+                uri: "https://a.com/".to_owned(),
+                ..UrlFrontierOptions::default()
+            }))
+            .data_store(data_store())
+            .seen(bloom_seen_set(100, 0.001))
+            .build();
+
+        crawl(deps.clone(), client, Arc::from([scope("https://a.com/")])).await;
+
+        let store = deps.data_store.read().await;
+        let mut keys = store.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["https://a.com/", "https://a.com/b"]);
+        assert_eq!(
+            store
+                .get(&"https://a.com/".to_owned())
+                .unwrap()
+                .urls_found
+                .len(),
+            4
+        );
+        assert_eq!(
+            url_inventory(&*store)[&UrlKind::NonHttp],
+            BTreeSet::from(["mailto:me@a.com".to_owned()])
+        );
+    }
+}
+
+#[cfg(test)]
+mod crawler_builder_tests {
+    use super::{Crawler, CrawlerOptions};
+    use crate::{
+        crawl_db::CrawlDb,
+        events::{CrawlEvent, FilterReason},
+        fetch::{Fetch, Response},
+        file::FileFetch,
+        output::ResponseWriter,
+        replay::ReplayFetch,
+        url::{QueryRules, UrlKind},
+    };
+    use futures::StreamExt;
+    use reqwest::{Error, IntoUrl};
+    use std::{
+        fs, io,
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+    use tokio::{sync::Notify, time::sleep};
+    use uuid::Uuid;
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    #[derive(Clone, Default)]
+    struct CountingFetch {
+        replay: ReplayFetch,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl Fetch for CountingFetch {
+        fn new() -> CountingFetch {
+            CountingFetch::default()
+        }
+
+        async fn get<T: IntoUrl + AsRef<str> + Send>(&self, url: T) -> Result<Response, Error> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            self.replay.get(url).await
+        }
+    }
+
+    fn response(url: &str, body: &str) -> Response {
+        Response {
+            url: url.to_owned(),
+            status: 200,
+            body: body.as_bytes().to_vec(),
+            ..Response::default()
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Archived(Arc<Mutex<Vec<String>>>);
+
+    impl ResponseWriter for Archived {
+        fn write(&mut self, response: &Response) -> io::Result<()> {
+            self.0.lock().unwrap().push(response.url.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn crawler_builder_rejects_missing_and_invalid_seeds() {
+        let missing = Crawler::builder().build().err().unwrap();
+        let invalid = Crawler::builder()
+            .seed("example.com")
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(missing.to_string(), "a crawl needs at least one seed");
+        assert_eq!(
+            invalid.to_string(),
+            "invalid seed example.com: relative URL without a base"
+        );
+    }
+
+    #[test]
+    fn crawler_builder_applies_query_rules_to_seeds() {
+        let crawler = Crawler::builder()
+            .seeds(["https://a.com/?utm_source=x", "https://a.com/"])
+            .query_rules(QueryRules::preset())
+            .build()
+            .unwrap();
+
+        assert_eq!(crawler.seeds(), ["https://a.com/"]);
+    }
+
+    #[tokio::test]
+    async fn crawler_runs_with_defaults_and_reports_every_site() {
+        let fetch = ReplayFetch::from_responses([
+            response(
+                "https://a.com/",
+                "<a href=\"/1\"></a><a href=\"/gone\"></a>",
+            ),
+            response("https://a.com/1", ""),
+            response("https://b.com/", ""),
+        ]);
+
+        let crawler = Crawler::builder()
+            .seeds(["https://a.com/", "https://b.com/", "https://a.com/"])
+            .delay(Duration::ZERO)
+            .fetch(fetch)
+            .build()
+            .unwrap();
+        assert_eq!(crawler.seeds(), ["https://a.com/", "https://b.com/"]);
+        assert_eq!(crawler.options().workers, CrawlerOptions::default().workers);
+
+        let report = crawler.run().await.unwrap();
+
+        assert_eq!(report.pages().count(), 4);
+        assert_eq!(report.links().len(), 2);
+        assert_eq!(
+            report
+                .broken_links()
+                .iter()
+                .map(|link| link.target.as_str())
+                .collect::<Vec<_>>(),
+            vec!["https://a.com/gone"]
+        );
+        assert_eq!(report.sites().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn crawler_keeps_every_site_within_its_own_scope() {
+        let fetch = ReplayFetch::from_responses([
+            response("https://a.com/", "<a href=\"https://b.com/x\"></a>"),
+            response("https://b.com/", ""),
+            response("https://b.com/x", ""),
+        ]);
+
+        let report = Crawler::builder()
+            .seeds(["https://a.com/", "https://b.com/"])
+            .delay(Duration::ZERO)
+            .fetch(fetch)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        let visited = report
+            .pages()
+            .filter(|(_, entry)| entry.visited)
+            .map(|(url, _)| url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(visited, vec!["https://a.com/", "https://b.com/"]);
+    }
+
+    #[tokio::test]
+    async fn crawler_streams_events_as_pages_are_crawled() {
+        let fetch = ReplayFetch::from_responses([
+            response(
+                "https://a.com/",
+                "<a href=\"/1\"></a><a href=\"mailto:me@a.com\"></a><a href=\"https://b.com/\"></a>",
+            ),
+            response("https://a.com/1", ""),
+        ]);
+
+        let events = Crawler::builder()
+            .seed("https://a.com/")
+            .delay(Duration::ZERO)
+            .events_buffer(1)
+            .fetch(fetch)
+            .build()
+            .unwrap()
+            .stream()
+            .collect::<Vec<_>>()
+            .await;
+
+        let summary = events
+            .iter()
+            .map(|event| match event {
+                CrawlEvent::PageFetched(record) => format!("fetched {}", record.url),
+                CrawlEvent::LinkDiscovered { url, .. } => format!("discovered {}", url),
+                CrawlEvent::UrlFiltered { url, reason, .. } => format!("{:?} {}", reason, url),
+                CrawlEvent::Error { error, .. } => format!("error {}", error),
+                CrawlEvent::Finished(report) => format!("finished {}", report.pages().count()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                "discovered https://a.com/1".to_owned(),
+                format!(
+                    "{:?} mailto:me@a.com",
+                    FilterReason::NotFetchable(UrlKind::NonHttp)
+                ),
+                format!("{:?} https://b.com/", FilterReason::OutOfScope),
+                "fetched https://a.com/".to_owned(),
+                "fetched https://a.com/1".to_owned(),
+                "finished 2".to_owned(),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crawler_waits_for_a_slow_stream_consumer() {
+        let pages = (0..10).map(|n| {
+            let link = if n < 9 {
+                format!("<a href=\"/{}\"></a>", n + 1)
+            } else {
+                String::new()
+            };
+            response(&format!("https://a.com/{}", n), &link)
+        });
+        let fetch = CountingFetch {
+            replay: ReplayFetch::from_responses(pages),
+            ..CountingFetch::default()
+        };
+        let requests = fetch.requests.clone();
+
+        let events = Crawler::builder()
+            .seed("https://a.com/0")
+            .delay(Duration::ZERO)
+            .events_buffer(1)
+            .fetch(fetch)
+            .build()
+            .unwrap()
+            .stream();
+        let release = Arc::new(Notify::new());
+        let consumer = tokio::spawn({
+            let release = release.clone();
+            async move {
+                release.notified().await;
+                events.collect::<Vec<_>>().await
+            }
+        });
+
+        // The paused clock only moves on once the crawl can't go any further
+        sleep(Duration::from_secs(60)).await;
+        // The link of the first page fills the buffer, and the crawl waits to send the page
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        release.notify_one();
+        let events = consumer.await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 10);
+        // A link and a page for every page but the last, which has no link, then the report
+        assert_eq!(events.len(), 20);
+        assert!(matches!(events.last(), Some(CrawlEvent::Finished(_))));
+    }
+
+    #[tokio::test]
+    async fn crawler_saves_its_db_and_resumes_from_it() {
+        let pages = || {
+            ReplayFetch::from_responses([
+                response("https://a.com/", "<a href=\"/1\"></a>"),
+                response("https://a.com/1", ""),
+            ])
+        };
+        let path = std::env::temp_dir().join(format!("crawl-{}.json", Uuid::new_v4()));
+
+        Crawler::builder()
+            .seed("https://a.com/")
+            .delay(Duration::ZERO)
+            .max_pages(1)
+            .db(&path)
+            .fetch(pages())
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        let checkpoint = CrawlDb::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint.pending(), vec!["https://a.com/1"]);
+
+        let report = Crawler::builder()
+            .seed("https://a.com/")
+            .delay(Duration::ZERO)
+            .resume(checkpoint)
+            .fetch(pages())
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        let visited = report
+            .pages()
+            .filter(|(_, entry)| entry.visited)
+            .map(|(url, _)| url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(visited, vec!["https://a.com/", "https://a.com/1"]);
+    }
+
+    #[tokio::test]
+    async fn crawler_does_not_archive_unchanged_responses() {
+        let fetch = ReplayFetch::from_responses([
+            Response {
+                unchanged: true,
+                ..response("https://a.com/", "<a href=\"/new\"></a>")
+            },
+            response("https://a.com/new", ""),
+        ]);
+        let archived = Archived::default();
+
+        let report = Crawler::builder()
+            .seed("https://a.com/")
+            .delay(Duration::ZERO)
+            .archive(archived.clone())
+            .fetch(fetch)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(*archived.0.lock().unwrap(), vec!["https://a.com/new"]);
+        let unchanged = report
+            .pages()
+            .filter(|(_, entry)| entry.page.as_ref().is_some_and(|page| page.unchanged))
+            .map(|(url, _)| url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(unchanged, vec!["https://a.com/"]);
+    }
+
+    #[tokio::test]
+    async fn crawler_checks_external_links_of_a_local_site_over_the_network() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let external = format!("{}/page", mock_server.uri());
+
+        let root = std::env::temp_dir().join(format!("site-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("index.html"),
+            format!("<a href=\"{}\"></a>", external),
+        )
+        .unwrap();
+        let seed = url::Url::from_directory_path(&root).unwrap().to_string();
+
+        let report = Crawler::builder()
+            .seed(&seed)
+            .delay(Duration::ZERO)
+            .external_links(true)
+            .fetch(FileFetch::with_root("/"))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        fs::remove_dir_all(root).unwrap();
+
+        let page = report.db.pages[&external].page.clone().unwrap();
+        assert_eq!(page.status, 200);
+        assert!(report.broken_links().is_empty());
+    }
+
+    #[tokio::test]
+    async fn crawler_reports_links_to_unreachable_pages_as_broken() {
+        // Nothing listens on a port that was just released
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let unreachable = format!("http://{}/", closed);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!("<a href=\"{}\"></a>", unreachable)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let report = Crawler::builder()
+            .seed(format!("{}/", mock_server.uri()))
+            .delay(Duration::ZERO)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        let entry = &report.db.pages[&unreachable];
+        assert!(entry.visited);
+        assert!(entry.page.as_ref().unwrap().error.is_some());
+        let broken = report.broken_links();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target, unreachable);
+        assert_eq!(report.db.seed_error(), None);
+    }
+
+    #[tokio::test]
+    async fn crawler_resolves_links_of_a_directory_without_trailing_slash_within_it() {
+        let root = std::env::temp_dir().join(format!("site-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("about")).unwrap();
+        fs::write(root.join("index.html"), "<a href=\"/about\"></a>").unwrap();
+        fs::write(root.join("about/index.html"), "<a href=\"team.html\"></a>").unwrap();
+        fs::write(root.join("about/team.html"), "").unwrap();
+
+        let report = Crawler::builder()
+            .seed("file:///")
+            .delay(Duration::ZERO)
+            .fetch(FileFetch::with_root(&root))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        fs::remove_dir_all(root).unwrap();
+
+        let team = report.db.pages["file:///about/team.html"].page.clone();
+        assert_eq!(team.unwrap().status, 200);
+        assert!(report.broken_links().is_empty());
     }
 }
@@ -1,22 +1,63 @@
+use crate::{parser::PageMetadata, url::UrlKind};
 use mockall::predicate::*;
 use mockall::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Page {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub content_hash: String,
+    pub metadata: PageMetadata,
+    // Set when the URL could not be requested, e.g. a DNS or connection failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // Topic relevance of the page text, only set for focused crawls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<f64>,
+    // The server answered 304 Not Modified, the rest is the cached response
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unchanged: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct DataStoreEntry<T> {
     pub visited: bool,
     pub urls_found: Vec<T>,
+    pub depth: usize,
+    pub referrer: Option<T>,
+    pub page: Option<Page>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<UrlKind>,
+}
+
+impl<T> Default for DataStoreEntry<T> {
+    fn default() -> Self {
+        DataStoreEntry {
+            visited: false,
+            urls_found: vec![],
+            depth: 0,
+            referrer: None,
+            page: None,
+            kind: None,
+        }
+    }
 }
 
 #[automock]
 pub trait DataStore<T, U: 'static>: Debug {
     fn add(&mut self, key: T, value: Option<U>);
+    fn discovered(&mut self, key: T, referrer: U, depth: usize);
     fn visited(&mut self, key: &T);
+    fn page(&mut self, key: &T, page: Page);
+    fn kind(&mut self, key: T, kind: UrlKind);
     fn has_visited(&self, key: &T) -> bool;
     fn exists(&self, key: &T) -> bool;
     fn get<'a>(&'a self, key: &T) -> Option<&'a DataStoreEntry<U>>;
+    fn keys(&self) -> Vec<T>;
 }
 
 #[derive(Debug, PartialEq, Default)]
@@ -30,6 +71,12 @@ impl<T: Clone + Hash + Eq, U> Store<T, U> {
             data: HashMap::<T, DataStoreEntry<U>>::new(),
         }
     }
+
+    pub fn from_entries(entries: impl IntoIterator<Item = (T, DataStoreEntry<U>)>) -> Self {
+        Store {
+            data: entries.into_iter().collect(),
+        }
+    }
 }
 
 impl<T: Debug + Clone + Hash + Eq, U: Debug + 'static> DataStore<T, U> for Store<T, U> {
@@ -38,17 +85,12 @@ impl<T: Debug + Clone + Hash + Eq, U: Debug + 'static> DataStore<T, U> for Store
 
         if let Some(item) = item {
             if let Some(value) = value {
-                return item.urls_found.push(value);
+                item.urls_found.push(value);
             }
+            return;
         }
 
-        self.data.insert(
-            key.clone(),
-            DataStoreEntry {
-                visited: false,
-                urls_found: vec![],
-            },
-        );
+        self.data.insert(key.clone(), DataStoreEntry::default());
 
         if let Some(value) = value {
             let item = self.data.get_mut(&key);
@@ -59,6 +101,17 @@ impl<T: Debug + Clone + Hash + Eq, U: Debug + 'static> DataStore<T, U> for Store
         }
     }
 
+    // Only the first referrer is kept, which is the shortest path when the frontier is FIFO.
+    // The entry may exist already with just the kind of the link.
+    fn discovered(&mut self, key: T, referrer: U, depth: usize) {
+        let entry = self.data.entry(key).or_default();
+
+        if entry.referrer.is_none() && !entry.visited {
+            entry.depth = depth;
+            entry.referrer = Some(referrer);
+        }
+    }
+
     fn exists(&self, key: &T) -> bool {
         if let Some(_val) = self.data.get(key) {
             return true;
@@ -78,17 +131,32 @@ impl<T: Debug + Clone + Hash + Eq, U: Debug + 'static> DataStore<T, U> for Store
         }
     }
 
+    fn page(&mut self, key: &T, page: Page) {
+        if let Some(item) = self.data.get_mut(key) {
+            item.page = Some(page)
+        }
+    }
+
+    // Links get an entry of their own so that non-http ones are inventoried too
+    fn kind(&mut self, key: T, kind: UrlKind) {
+        self.data.entry(key).or_default().kind = Some(kind);
+    }
+
     fn has_visited(&self, key: &T) -> bool {
         if let Some(key) = self.data.get(key) {
             return key.visited;
         }
         false
     }
+
+    fn keys(&self) -> Vec<T> {
+        self.data.keys().cloned().collect()
+    }
 }
 
 #[cfg(test)]
 mod data_store_tests {
-    use crate::data_store::DataStoreEntry;
+    use crate::{data_store::DataStoreEntry, url::UrlKind};
 
     use super::{DataStore, Store};
 
@@ -105,7 +173,8 @@ mod data_store_tests {
             s.get(&key),
             Some(&DataStoreEntry {
                 visited: false,
-                urls_found: vec![val]
+                urls_found: vec![val],
+                ..DataStoreEntry::default()
             })
         );
     }
@@ -125,7 +194,8 @@ mod data_store_tests {
             s.get(&key),
             Some(&DataStoreEntry {
                 visited: false,
-                urls_found: vec![val, val2]
+                urls_found: vec![val, val2],
+                ..DataStoreEntry::default()
             })
         );
     }
@@ -142,7 +212,8 @@ mod data_store_tests {
             s.get(&key),
             Some(&DataStoreEntry {
                 visited: false,
-                urls_found: vec![]
+                urls_found: vec![],
+                ..DataStoreEntry::default()
             })
         );
     }
@@ -169,4 +240,41 @@ mod data_store_tests {
 
         assert!(s.has_visited(&key.clone()));
     }
+
+    #[test]
+    fn data_store_keeps_first_referrer_and_depth_of_discovered_key() {
+        let mut s = Store::new();
+        let key = "key".to_owned();
+
+        s.kind(key.clone(), UrlKind::Page);
+        s.discovered(key.clone(), "first".to_owned(), 1);
+        s.discovered(key.clone(), "second".to_owned(), 2);
+        s.add(key.clone(), None);
+
+        assert!(!s.has_visited(&key));
+        assert_eq!(
+            s.get(&key),
+            Some(&DataStoreEntry {
+                depth: 1,
+                referrer: Some("first".to_owned()),
+                kind: Some(UrlKind::Page),
+                ..DataStoreEntry::default()
+            })
+        );
+    }
+
+    #[test]
+    fn data_store_records_kind_of_links_without_visiting_them() {
+        let mut s = Store::new();
+        let key = "mailto:me@example.com".to_owned();
+
+        s.add("/".to_owned(), Some(key.clone()));
+        s.kind(key.clone(), UrlKind::NonHttp);
+
+        assert!(!s.has_visited(&key));
+        assert_eq!(
+            s.get(&key).and_then(|entry| entry.kind),
+            Some(UrlKind::NonHttp)
+        );
+    }
 }
@@ -1,12 +1,34 @@
 use crate::{
     data_store::{DataStore, Store},
+    events::CrawlEvent,
+    focus::TopicFocus,
+    hooks::CrawlHooks,
+    output::{JsonLinesWriter, PageWriter, ResponseWriter},
+    priority_frontier::{PriorityFrontier, UrlScorer},
+    record::RecordDir,
+    seen::{HashSeenSet, ScalableBloomFilter, SeenSet},
+    site_frontier::{Site, SiteFrontier},
+    spilling_frontier::SpillingFrontier,
+    trap::{TrapDetector, TrapOptions},
+    url::QueryRules,
     url_frontier::{Queue, URLFrontierBuilder},
+    warc::WarcWriter,
 };
-use std::{fmt::Debug, hash::Hash, ops::Deref, sync::Arc};
-use tokio::sync::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    hash::Hash,
+    io::{BufWriter, Error},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::{mpsc::Sender, RwLock};
 
-pub type Deps<T, U> = Arc<RwLock<Dependencies<T, U>>>;
-pub type DepsConcrete = Arc<Dependencies<String, String>>;
+// What Dependencies::build returns, shared by every crawl task
+pub type Deps<T, U> = Arc<Dependencies<T, U>>;
+pub type DepsConcrete = Deps<String, String>;
 
 // Implement the Deref trait in order to access impl Queue without having to .0
 impl<T> Deref for Frontier<T> {
@@ -36,6 +58,29 @@ pub fn url_frontier<T: Send + Sync + Default + 'static>(
     Frontier(Arc::new(RwLock::new(url_frontier)))
 }
 
+pub fn priority_frontier<T: Clone + Hash + Eq + Send + Sync + 'static>(
+    opts: UrlFrontierOptions<T>,
+    scorer: impl UrlScorer<T> + Send + Sync + 'static,
+) -> Frontier<T> {
+    let url_frontier = PriorityFrontier::new(scorer)
+        .delay_s(opts.delay_s.unwrap_or(0))
+        .value(opts.uri);
+
+    Frontier(Arc::new(RwLock::new(url_frontier)))
+}
+
+pub fn spilling_frontier<T: Serialize + DeserializeOwned + Send + Sync + 'static>(
+    opts: UrlFrontierOptions<T>,
+    dir: impl Into<PathBuf>,
+    buffer_size: usize,
+) -> Result<Frontier<T>, Error> {
+    let url_frontier = SpillingFrontier::new(dir, buffer_size)?
+        .delay_s(opts.delay_s.unwrap_or(0))
+        .value(opts.uri);
+
+    Ok(Frontier(Arc::new(RwLock::new(url_frontier))))
+}
+
 // Implement the Deref trait in order to access impl Queue without having to .0
 impl<T, U> Deref for MemoryStore<T, U> {
     type Target = Arc<RwLock<dyn DataStore<T, U> + Send + Sync>>;
@@ -55,6 +100,117 @@ pub fn data_store<
     MemoryStore(Arc::new(RwLock::new(store)))
 }
 
+// A store carrying on from the entries of an earlier crawl
+pub fn restored_store<
+    T: Hash + Eq + Clone + Debug + Send + Sync + 'static,
+    U: Send + Sync + Debug + 'static,
+>(
+    store: Store<T, U>,
+) -> MemoryStore<T, U> {
+    MemoryStore(Arc::new(RwLock::new(store)))
+}
+
+// Implement the Deref trait in order to access impl PageWriter without having to .0
+impl Deref for Output {
+    type Target = Arc<RwLock<dyn PageWriter + Send + Sync>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Output(pub Arc<RwLock<dyn PageWriter + Send + Sync>>);
+
+pub fn json_lines_output(path: impl AsRef<Path>) -> Result<Output, Error> {
+    let writer = JsonLinesWriter::new(BufWriter::new(File::create(path)?));
+    Ok(Output(Arc::new(RwLock::new(writer))))
+}
+
+// Implement the Deref trait in order to access impl ResponseWriter without having to .0
+impl Deref for Archive {
+    type Target = Arc<RwLock<dyn ResponseWriter + Send + Sync>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Archive(pub Arc<RwLock<dyn ResponseWriter + Send + Sync>>);
+
+pub fn warc_archive(prefix: impl Into<PathBuf>, max_size: u64) -> Archive {
+    Archive(Arc::new(RwLock::new(WarcWriter::new(prefix, max_size))))
+}
+
+pub fn record_archive(path: impl Into<PathBuf>) -> Result<Archive, Error> {
+    Ok(Archive(Arc::new(RwLock::new(RecordDir::new(path)?))))
+}
+
+// Implement the Deref trait in order to access impl CrawlHooks without having to .0
+impl Deref for Hooks {
+    type Target = Arc<RwLock<dyn CrawlHooks + Send + Sync>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Hooks(pub Arc<RwLock<dyn CrawlHooks + Send + Sync>>);
+
+pub fn crawl_hooks(hooks: impl CrawlHooks + Send + Sync + 'static) -> Hooks {
+    Hooks(Arc::new(RwLock::new(hooks)))
+}
+
+// Implement the Deref trait in order to access impl SeenSet without having to .0
+impl Deref for Seen {
+    type Target = Arc<RwLock<dyn SeenSet + Send + Sync>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Seen(pub Arc<RwLock<dyn SeenSet + Send + Sync>>);
+
+pub fn hash_seen_set() -> Seen {
+    Seen(Arc::new(RwLock::new(HashSeenSet::default())))
+}
+
+pub fn bloom_seen_set(capacity: usize, fp_rate: f64) -> Seen {
+    Seen(Arc::new(RwLock::new(ScalableBloomFilter::new(
+        capacity, fp_rate,
+    ))))
+}
+
+// Implement the Deref trait in order to access TrapDetector without having to .0
+impl Deref for Traps {
+    type Target = Arc<RwLock<TrapDetector>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Traps(pub Arc<RwLock<TrapDetector>>);
+
+pub fn trap_detector(options: TrapOptions) -> Traps {
+    Traps(Arc::new(RwLock::new(TrapDetector::new(options))))
+}
+
+// Implement the Deref trait in order to access SiteFrontier without having to .0
+impl Deref for Sites {
+    type Target = Arc<RwLock<SiteFrontier>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Sites(pub Arc<RwLock<SiteFrontier>>);
+
+pub fn site_frontier(sites: Vec<Site>) -> Sites {
+    Sites(Arc::new(RwLock::new(SiteFrontier::new(sites))))
+}
+
 impl<
         T: Clone + Default + Debug + Hash + Eq + Send + Sync + 'static,
         U: Send + Debug + Sync + Default + 'static,
@@ -74,34 +230,119 @@ impl<
         Dependencies {
             url_frontier: url_frontier(UrlFrontierOptions::default()),
             data_store: data_store(),
+            output: None,
+            archive: None,
+            focus: None,
+            seen: None,
+            traps: None,
+            query_rules: QueryRules::default(),
+            sites: None,
+            events: None,
+            hooks: None,
         }
     }
 
     pub fn url_frontier(self, url_frontier: Frontier<T>) -> Dependencies<T, U> {
-        let Self { data_store, .. } = self;
         Dependencies {
             url_frontier,
-            data_store,
+            ..self
         }
     }
 
     pub fn data_store(self, data_store: MemoryStore<T, U>) -> Dependencies<T, U> {
-        let Self { url_frontier, .. } = self;
+        Dependencies { data_store, ..self }
+    }
+
+    pub fn output(self, output: Output) -> Dependencies<T, U> {
         Dependencies {
-            url_frontier,
-            data_store,
+            output: Some(output),
+            ..self
+        }
+    }
+
+    pub fn archive(self, archive: Archive) -> Dependencies<T, U> {
+        Dependencies {
+            archive: Some(archive),
+            ..self
+        }
+    }
+
+    pub fn focus(self, focus: Arc<TopicFocus>) -> Dependencies<T, U> {
+        Dependencies {
+            focus: Some(focus),
+            ..self
+        }
+    }
+
+    pub fn seen(self, seen: Seen) -> Dependencies<T, U> {
+        Dependencies {
+            seen: Some(seen),
+            ..self
+        }
+    }
+
+    pub fn traps(self, traps: Traps) -> Dependencies<T, U> {
+        Dependencies {
+            traps: Some(traps),
+            ..self
+        }
+    }
+
+    pub fn query_rules(self, query_rules: QueryRules) -> Dependencies<T, U> {
+        Dependencies {
+            query_rules,
+            ..self
         }
     }
 
-    pub fn build(self) -> Arc<Dependencies<T, U>> {
-        Arc::new(Self {
-            url_frontier: self.url_frontier,
-            data_store: self.data_store,
-        })
+    // Events are sent as the crawl goes, waiting for room in the channel
+    pub fn events(self, events: Sender<CrawlEvent>) -> Dependencies<T, U> {
+        Dependencies {
+            events: Some(events),
+            ..self
+        }
+    }
+
+    pub fn hooks(self, hooks: Hooks) -> Dependencies<T, U> {
+        Dependencies {
+            hooks: Some(hooks),
+            ..self
+        }
+    }
+
+    // Sites count every URL they hand out against their budget, so a crawl of sites
+    // enqueues every URL once. Without a Bloom filter the URLs are remembered exactly.
+    pub fn build(self) -> Deps<T, U> {
+        let seen = match (&self.sites, self.seen) {
+            (Some(_), None) => Some(hash_seen_set()),
+            (_, seen) => seen,
+        };
+
+        Arc::new(Dependencies { seen, ..self })
+    }
+}
+
+impl<U> Dependencies<String, U> {
+    // The sites share one frontier, which replaces the url_frontier
+    pub fn sites(self, sites: Sites) -> Dependencies<String, U> {
+        Dependencies {
+            url_frontier: Frontier(sites.0.clone()),
+            sites: Some(sites),
+            ..self
+        }
     }
 }
 
 pub struct Dependencies<T: Clone + Hash + Eq, U> {
     pub url_frontier: Frontier<T>,
     pub data_store: MemoryStore<T, U>,
+    pub output: Option<Output>,
+    pub archive: Option<Archive>,
+    pub focus: Option<Arc<TopicFocus>>,
+    pub seen: Option<Seen>,
+    pub traps: Option<Traps>,
+    pub query_rules: QueryRules,
+    pub sites: Option<Sites>,
+    pub events: Option<Sender<CrawlEvent>>,
+    pub hooks: Option<Hooks>,
 }
@@ -0,0 +1,272 @@
+use crate::{
+    crawl_db::CrawlDb,
+    data_store::Page,
+    report::{broken_links, LinkCheck},
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Change<T> {
+    pub url: String,
+    pub field: String,
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub struct CrawlDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub status_changes: Vec<Change<u16>>,
+    pub metadata_changes: Vec<Change<Option<String>>>,
+    pub content_changes: Vec<String>,
+    pub new_broken_links: Vec<LinkCheck>,
+}
+
+impl CrawlDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.status_changes.is_empty()
+            && self.metadata_changes.is_empty()
+            && self.content_changes.is_empty()
+            && self.new_broken_links.is_empty()
+    }
+}
+
+fn pages(db: &CrawlDb) -> impl Iterator<Item = (&String, Page)> {
+    db.visited()
+        .map(|(url, entry)| (url, entry.page.clone().unwrap_or_default()))
+}
+
+pub fn diff(old: &CrawlDb, new: &CrawlDb) -> CrawlDiff {
+    let mut diff = CrawlDiff::default();
+
+    for (url, new_page) in pages(new) {
+        let Some(old_page) = old
+            .pages
+            .get(url)
+            .filter(|entry| entry.visited)
+            .map(|entry| entry.page.clone().unwrap_or_default())
+        else {
+            diff.added.push(url.clone());
+            continue;
+        };
+
+        if old_page.status != new_page.status {
+            diff.status_changes.push(Change {
+                url: url.clone(),
+                field: "status".to_owned(),
+                old: old_page.status,
+                new: new_page.status,
+            });
+        }
+
+        let fields = [
+            ("title", old_page.metadata.title, new_page.metadata.title),
+            (
+                "canonical",
+                old_page.metadata.canonical,
+                new_page.metadata.canonical,
+            ),
+            (
+                "description",
+                old_page.metadata.description,
+                new_page.metadata.description,
+            ),
+        ];
+        for (field, old_value, new_value) in fields {
+            if old_value != new_value {
+                diff.metadata_changes.push(Change {
+                    url: url.clone(),
+                    field: field.to_owned(),
+                    old: old_value,
+                    new: new_value,
+                });
+            }
+        }
+
+        if old_page.content_hash != new_page.content_hash {
+            diff.content_changes.push(url.clone());
+        }
+    }
+
+    for (url, _) in old.visited() {
+        if !new.pages.get(url).is_some_and(|entry| entry.visited) {
+            diff.removed.push(url.clone());
+        }
+    }
+
+    let old_broken_links = broken_links(old)
+        .into_iter()
+        .map(|link| (link.source, link.target))
+        .collect::<BTreeSet<_>>();
+    diff.new_broken_links = broken_links(new)
+        .into_iter()
+        .filter(|link| !old_broken_links.contains(&(link.source.clone(), link.target.clone())))
+        .collect();
+
+    diff
+}
+
+fn write_changes<T: Debug>(f: &mut Formatter<'_>, changes: &[Change<T>]) -> FmtResult {
+    for change in changes {
+        writeln!(
+            f,
+            "  {} {}: {:?} -> {:?}",
+            change.url, change.field, change.old, change.new
+        )?;
+    }
+    Ok(())
+}
+
+impl Display for CrawlDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "Added ({}):", self.added.len())?;
+        for url in &self.added {
+            writeln!(f, "  + {}", url)?;
+        }
+
+        writeln!(f, "Removed ({}):", self.removed.len())?;
+        for url in &self.removed {
+            writeln!(f, "  - {}", url)?;
+        }
+
+        writeln!(f, "Status changes ({}):", self.status_changes.len())?;
+        write_changes(f, &self.status_changes)?;
+
+        writeln!(f, "Metadata changes ({}):", self.metadata_changes.len())?;
+        write_changes(f, &self.metadata_changes)?;
+
+        writeln!(f, "Content changes ({}):", self.content_changes.len())?;
+        for url in &self.content_changes {
+            writeln!(f, "  ~ {}", url)?;
+        }
+
+        write!(f, "New broken links ({}):", self.new_broken_links.len())?;
+        for link in &self.new_broken_links {
+            write!(f, "\n  {}", link)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::{diff, Change};
+    use crate::{
+        crawl_db::CrawlDb,
+        data_store::{DataStoreEntry, Page},
+        parser::PageMetadata,
+        report::LinkCheck,
+    };
+
+    fn page(
+        status: u16,
+        title: &str,
+        content_hash: &str,
+        links: Vec<&str>,
+    ) -> DataStoreEntry<String> {
+        DataStoreEntry {
+            visited: true,
+            urls_found: links.into_iter().map(|link| link.to_owned()).collect(),
+            page: Some(Page {
+                status,
+                content_hash: content_hash.to_owned(),
+                metadata: PageMetadata {
+                    title: Some(title.to_owned()),
+                    ..PageMetadata::default()
+                },
+                ..Page::default()
+            }),
+            ..DataStoreEntry::default()
+        }
+    }
+
+    fn db(pages: Vec<(&str, DataStoreEntry<String>)>) -> CrawlDb {
+        CrawlDb {
+            pages: pages
+                .into_iter()
+                .map(|(url, entry)| (url.to_owned(), entry))
+                .collect(),
+            ..CrawlDb::default()
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_and_removed_urls() {
+        let old = db(vec![
+            ("/", page(200, "Home", "1", vec![])),
+            ("/old", page(200, "Old", "1", vec![])),
+        ]);
+        let new = db(vec![
+            ("/", page(200, "Home", "1", vec![])),
+            ("/new", page(200, "New", "1", vec![])),
+        ]);
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.added, vec!["/new".to_owned()]);
+        assert_eq!(diff.removed, vec!["/old".to_owned()]);
+        assert!(diff.status_changes.is_empty());
+    }
+
+    #[test]
+    fn diff_reports_status_metadata_and_content_changes() {
+        let old = db(vec![("/", page(200, "Home", "1", vec![]))]);
+        let new = db(vec![("/", page(500, "Welcome", "2", vec![]))]);
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.status_changes,
+            vec![Change {
+                url: "/".to_owned(),
+                field: "status".to_owned(),
+                old: 200,
+                new: 500
+            }]
+        );
+        assert_eq!(
+            diff.metadata_changes,
+            vec![Change {
+                url: "/".to_owned(),
+                field: "title".to_owned(),
+                old: Some("Home".to_owned()),
+                new: Some("Welcome".to_owned())
+            }]
+        );
+        assert_eq!(diff.content_changes, vec!["/".to_owned()]);
+    }
+
+    #[test]
+    fn diff_reports_only_new_broken_links() {
+        let old = db(vec![
+            ("/", page(200, "Home", "1", vec!["/a"])),
+            ("/a", page(404, "", "1", vec![])),
+            ("/b", page(200, "B", "1", vec![])),
+        ]);
+        let new = db(vec![
+            ("/", page(200, "Home", "1", vec!["/a", "/b"])),
+            ("/a", page(404, "", "1", vec![])),
+            ("/b", page(404, "B", "1", vec![])),
+        ]);
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.new_broken_links,
+            vec![LinkCheck {
+                source: "/".to_owned(),
+                target: "/b".to_owned(),
+                status: 404,
+                error: None
+            }]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{crawler::CrawlReport, output::PageRecord, trap::Trap, url::UrlKind};
+use futures::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{sync::mpsc::Receiver, task::JoinHandle};
+
+// Why a link found on a page is not crawled
+#[derive(Debug, PartialEq, Clone)]
+pub enum FilterReason {
+    // The link can't be parsed, it shows up as a broken link
+    Invalid(String),
+    // mailto:, tel: and the like
+    NotFetchable(UrlKind),
+    OutOfScope,
+    // A focused crawl stopped following links of irrelevant pages
+    Irrelevant,
+    Trap(Trap),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CrawlEvent {
+    // Sent once the links of the page were followed, so the record lists all of them
+    PageFetched(PageRecord),
+    LinkDiscovered {
+        source: String,
+        url: String,
+        depth: usize,
+    },
+    UrlFiltered {
+        source: String,
+        url: String,
+        reason: FilterReason,
+    },
+    Error {
+        url: Option<String>,
+        error: String,
+    },
+    // The last event, unless the crawl failed with an Error without a URL
+    Finished(CrawlReport),
+}
+
+// The events of a running crawl. The crawl waits while the buffer is full, and stops
+// requesting pages once the stream is dropped.
+pub struct CrawlEvents {
+    receiver: Receiver<CrawlEvent>,
+    task: JoinHandle<()>,
+}
+
+impl CrawlEvents {
+    pub fn new(receiver: Receiver<CrawlEvent>, task: JoinHandle<()>) -> Self {
+        CrawlEvents { receiver, task }
+    }
+}
+
+impl Stream for CrawlEvents {
+    type Item = CrawlEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CrawlEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for CrawlEvents {
+    fn drop(&mut self) {
+        // The workers are spawned tasks of their own, they stop once they see the closed channel
+        self.task.abort();
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{crawl_db::CrawlDb, data_store::DataStoreEntry, junit::escape, report::LinkCheck};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct ExportedPage<'a> {
+    url: &'a str,
+    #[serde(flatten)]
+    entry: &'a DataStoreEntry<String>,
+}
+
+// One JSON object per crawled page, its URL along with everything stored for it
+pub fn pages_jsonl(db: &CrawlDb) -> serde_json::Result<String> {
+    let mut jsonl = String::new();
+
+    for (url, entry) in db.visited() {
+        jsonl.push_str(&serde_json::to_string(&ExportedPage { url, entry })?);
+        jsonl.push('\n');
+    }
+
+    Ok(jsonl)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+pub fn links_csv(links: &[LinkCheck]) -> String {
+    let mut csv = String::from("source,target,status,error\n");
+
+    for link in links {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&link.source),
+            csv_field(&link.target),
+            link.status,
+            csv_field(link.error.as_deref().unwrap_or_default())
+        ));
+    }
+
+    csv
+}
+
+// The crawled pages that answered 200, with the lastmod of the site's own sitemap if any
+pub fn sitemap_xml(db: &CrawlDb) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for (url, entry) in db.visited() {
+        if entry.page.as_ref().map(|page| page.status) != Some(200) {
+            continue;
+        }
+
+        xml.push_str(&format!("  <url><loc>{}</loc>", escape(url)));
+        if let Some(Some(lastmod)) = db.sitemap.get(url) {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", escape(lastmod)));
+        }
+        xml.push_str("</url>\n");
+    }
+
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::{links_csv, pages_jsonl, sitemap_xml};
+    use crate::{
+        crawl_db::CrawlDb,
+        data_store::{DataStore, Page, Store},
+        report::LinkCheck,
+    };
+
+    fn db() -> CrawlDb {
+        let mut store = Store::new();
+        store.add(
+            "https://a.com/".to_owned(),
+            Some("https://a.com/x".to_owned()),
+        );
+        store.visited(&"https://a.com/".to_owned());
+        store.page(
+            &"https://a.com/".to_owned(),
+            Page {
+                status: 200,
+                ..Page::default()
+            },
+        );
+        store.add("https://a.com/x".to_owned(), None);
+
+        let mut db = CrawlDb::from_store("https://a.com/", &store);
+        db.sitemap
+            .insert("https://a.com/".to_owned(), Some("2024-01-01".to_owned()));
+        db
+    }
+
+    #[test]
+    fn pages_jsonl_and_sitemap_xml_list_crawled_pages_only() {
+        let db = db();
+
+        let jsonl = pages_jsonl(&db).unwrap();
+        let sitemap = sitemap_xml(&db);
+
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.starts_with("{\"url\":\"https://a.com/\",\"visited\":true,"));
+        assert!(sitemap.contains(
+            "<url><loc>https://a.com/</loc><lastmod>2024-01-01</lastmod></url>\n</urlset>"
+        ));
+        assert!(!sitemap.contains("https://a.com/x"));
+    }
+
+    #[test]
+    fn links_csv_quotes_fields_with_commas_and_quotes() {
+        let links = [LinkCheck {
+            source: "https://a.com/".to_owned(),
+            target: "https://a.com/?a=1,2".to_owned(),
+            status: 0,
+            error: Some("bad \"url\"".to_owned()),
+        }];
+
+        assert_eq!(
+            links_csv(&links),
+            "source,target,status,error\nhttps://a.com/,\"https://a.com/?a=1,2\",0,\"bad \"\"url\"\"\"\n"
+        );
+    }
+}
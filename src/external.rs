@@ -0,0 +1,247 @@
+use crate::{
+    data_store::Page,
+    dependencies::DepsConcrete,
+    fetch::{Fetch, Response},
+    url::{filter_url_in, site_scope, Scope},
+};
+use ::url::Url;
+use log::{info, warn};
+use reqwest::Error;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::Semaphore, task::JoinSet, time::sleep};
+
+// Hosts checked at the same time, a page may link to thousands of them
+const MAX_HOSTS: usize = 16;
+
+// Only http(s) links can be checked, mailto: and the like have no host to request
+fn host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+
+    match url.scheme() {
+        "http" | "https" => url.host_str().map(|host| host.to_owned()),
+        _ => None,
+    }
+}
+
+// Every http(s) link found on a crawled page that falls outside the scope of the page's
+// site and has not been checked yet, grouped by host
+pub async fn external_links(
+    deps: &DepsConcrete,
+    scopes: Arc<[Scope]>,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let data_store = deps.data_store.read().await;
+    let mut links: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for key in data_store.keys() {
+        let Some(entry) = data_store.get(&key).filter(|entry| entry.visited) else {
+            continue;
+        };
+
+        for url in &entry.urls_found {
+            let Some(host) = host(url) else {
+                continue;
+            };
+            let checked = data_store
+                .get(url)
+                .is_some_and(|entry| entry.page.is_some());
+
+            let external = filter_url_in(url.clone(), site_scope(&key, &scopes));
+            if !checked && matches!(external, Ok(None)) {
+                links.entry(host).or_default().insert(url.clone());
+            }
+        }
+    }
+
+    links
+}
+
+// A HEAD request is enough to know the status, servers that don't allow it get a GET
+async fn check<F: Fetch>(http: &F, url: &str) -> Result<Response, Error> {
+    match http.head(url).await {
+        Ok(response) if response.status == 405 || response.status == 501 => http.get(url).await,
+        result => result,
+    }
+}
+
+async fn verify<F: Fetch>(deps: &DepsConcrete, http: &F, url: &str) {
+    let page = match check(http, url).await {
+        Ok(response) => Page {
+            status: response.status,
+            content_type: response.content_type().map(|value| value.to_owned()),
+            ..Page::default()
+        },
+        Err(e) => {
+            warn!("Error requesting external URL {} - {:?}", url, e);
+            Page {
+                error: Some(e.to_string()),
+                ..Page::default()
+            }
+        }
+    };
+
+    info!("Checked external URL: {} ({})", url, page.status);
+
+    let mut data_store = deps.data_store.write().await;
+    data_store.add(url.to_owned(), None);
+    data_store.page(&url.to_owned(), page);
+}
+
+// Requests each external link once and stores the outcome on its node. The response is
+// never parsed, so the crawl does not spread to other sites. Up to MAX_HOSTS hosts are
+// checked in parallel, with `delay` between two requests to the same host.
+pub async fn verify_external_links<F: Fetch + Clone + Send + Sync + 'static>(
+    deps: &DepsConcrete,
+    http: &F,
+    scopes: Arc<[Scope]>,
+    delay: Duration,
+) {
+    let mut tasks = JoinSet::new();
+    let hosts = Arc::new(Semaphore::new(MAX_HOSTS));
+
+    for (_host, urls) in external_links(deps, scopes).await {
+        let deps = deps.clone();
+        let http = http.clone();
+        let hosts = hosts.clone();
+
+        tasks.spawn(async move {
+            let Ok(_permit) = hosts.acquire_owned().await else {
+                return;
+            };
+            for (n, url) in urls.iter().enumerate() {
+                if n > 0 {
+                    sleep(delay).await;
+                }
+                verify(&deps, &http, url).await;
+            }
+        });
+    }
+
+    while tasks.join_next().await.is_some() {}
+}
+
+#[cfg(test)]
+mod external_tests {
+    use super::verify_external_links;
+    use crate::{
+        dependencies::Dependencies,
+        fetch::{Fetch, HttpFetch, Response},
+        replay::ReplayFetch,
+        url::scope,
+    };
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn verify_external_links_stores_status_without_visiting() {
+        let deps = Dependencies::new().build();
+        {
+            let mut store = deps.data_store.write().await;
+            store.add(
+                "https://example.com/".to_owned(),
+                Some("https://example.com/about".to_owned()),
+            );
+            store.add(
+                "https://example.com/".to_owned(),
+                Some("https://other.com/missing".to_owned()),
+            );
+            store.add(
+                "https://example.com/".to_owned(),
+                Some("mailto:someone@example.com".to_owned()),
+            );
+            store.visited(&"https://example.com/".to_owned());
+        }
+        let http = ReplayFetch::from_responses(vec![Response {
+            url: "https://other.com/missing".to_owned(),
+            status: 404,
+            ..Response::default()
+        }]);
+        let parts = Arc::from([scope("https://example.com/")]);
+
+        verify_external_links(&deps, &http, parts, Duration::ZERO).await;
+
+        let store = deps.data_store.read().await;
+        let external = store.get(&"https://other.com/missing".to_owned()).unwrap();
+        assert!(!external.visited);
+        assert_eq!(external.page.as_ref().map(|page| page.status), Some(404));
+        assert!(!store.exists(&"https://example.com/about".to_owned()));
+        assert!(!store.exists(&"mailto:someone@example.com".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn verify_external_links_requests_each_link_once_and_waits_between_requests() {
+        let mock_server = MockServer::start().await;
+        let deps = Dependencies::new().build();
+        {
+            let mut store = deps.data_store.write().await;
+            for page in ["https://example.com/", "https://example.com/about"] {
+                store.add(page.to_owned(), Some(format!("{}/a", mock_server.uri())));
+                store.add(page.to_owned(), Some(format!("{}/b", mock_server.uri())));
+                store.visited(&page.to_owned());
+            }
+        }
+
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let start = Instant::now();
+        verify_external_links(
+            &deps,
+            &HttpFetch::new(),
+            Arc::from([scope("https://example.com/")]),
+            Duration::from_millis(200),
+        )
+        .await;
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let store = deps.data_store.read().await;
+        let checked = store.get(&format!("{}/b", mock_server.uri())).unwrap();
+        assert_eq!(checked.page.as_ref().map(|page| page.status), Some(200));
+    }
+
+    #[tokio::test]
+    async fn verify_external_links_falls_back_to_get_when_head_is_not_allowed() {
+        let mock_server = MockServer::start().await;
+        let deps = Dependencies::new().build();
+        {
+            let mut store = deps.data_store.write().await;
+            store.add(
+                "https://example.com/".to_owned(),
+                Some(format!("{}/a", mock_server.uri())),
+            );
+            store.visited(&"https://example.com/".to_owned());
+        }
+
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(405))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        verify_external_links(
+            &deps,
+            &HttpFetch::new(),
+            Arc::from([scope("https://example.com/")]),
+            Duration::ZERO,
+        )
+        .await;
+
+        let store = deps.data_store.read().await;
+        let checked = store.get(&format!("{}/a", mock_server.uri())).unwrap();
+        assert_eq!(checked.page.as_ref().map(|page| page.status), Some(200));
+    }
+}
@@ -1,18 +1,144 @@
-use reqwest::{Client, Error, IntoUrl};
+use reqwest::{header::HeaderMap, Client, Error, IntoUrl, RequestBuilder};
+use std::time::{Duration, Instant};
+
+pub type Headers = Vec<(String, String)>;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Response {
+    // Where the response came from, after following redirects
+    pub url: String,
+    // The URL that was requested, empty when it is the same as `url`
+    pub requested_url: String,
+    pub version: String,
+    pub status: u16,
+    pub reason: String,
+    pub request_headers: Headers,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+    pub elapsed: Duration,
+    pub unchanged: bool,
+}
+
+impl Response {
+    pub fn not_found(url: &str) -> Response {
+        Response {
+            url: url.to_owned(),
+            version: "HTTP/1.1".to_owned(),
+            status: 404,
+            reason: "Not Found".to_owned(),
+            ..Response::default()
+        }
+    }
+
+    pub fn requested(&self) -> &str {
+        if self.requested_url.is_empty() {
+            &self.url
+        } else {
+            &self.requested_url
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn size(&self) -> usize {
+        self.body.len()
+    }
+}
+
+fn headers(header_map: &HeaderMap) -> Headers {
+    header_map
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.as_str().to_owned(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
 
 pub trait Fetch {
     fn new() -> Self;
-    fn get<T: IntoUrl + Send>(
+    fn get<T: IntoUrl + AsRef<str> + Send>(
         &self,
         url: T,
-    ) -> impl std::future::Future<Output = Result<String, Error>> + std::marker::Send;
+    ) -> impl std::future::Future<Output = Result<Response, Error>> + std::marker::Send;
+
+    // Only the status and headers matter, e.g. to check a link. Fetches without a cheaper
+    // way to get them make a GET.
+    fn head<T: IntoUrl + AsRef<str> + Send>(
+        &self,
+        url: T,
+    ) -> impl std::future::Future<Output = Result<Response, Error>> + std::marker::Send {
+        self.get(url)
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct HttpFetch {
     client: Client,
 }
 
+impl HttpFetch {
+    pub async fn get_with_headers<T: IntoUrl + Send>(
+        &self,
+        url: T,
+        extra_headers: &Headers,
+    ) -> Result<Response, Error> {
+        let mut request = self.client.get(url);
+        for (name, value) in extra_headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        self.send(request).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let request = request.build()?;
+        let request_headers = headers(request.headers());
+        let requested_url = request.url().to_string();
+
+        let start = Instant::now();
+        let response = self.client.execute(request).await?;
+
+        let url = response.url().to_string();
+        let version = format!("{:?}", response.version());
+        let status = response.status();
+        let headers = headers(response.headers());
+        let body = response.bytes().await?.to_vec();
+
+        Ok(Response {
+            requested_url: if requested_url == url {
+                String::new()
+            } else {
+                requested_url
+            },
+            url,
+            version,
+            status: status.as_u16(),
+            reason: status.canonical_reason().unwrap_or_default().to_owned(),
+            request_headers,
+            headers,
+            body,
+            elapsed: start.elapsed(),
+            unchanged: false,
+        })
+    }
+}
+
 impl Fetch for HttpFetch {
     fn new() -> HttpFetch {
         HttpFetch {
@@ -20,15 +146,21 @@ impl Fetch for HttpFetch {
         }
     }
 
-    async fn get<T: IntoUrl + Send>(&self, url: T) -> Result<String, Error> {
-        let response = self.client.get(url).send().await?;
-        response.text().await
+    async fn get<T: IntoUrl + AsRef<str> + Send>(&self, url: T) -> Result<Response, Error> {
+        self.get_with_headers(url, &vec![]).await
+    }
+
+    async fn head<T: IntoUrl + AsRef<str> + Send>(&self, url: T) -> Result<Response, Error> {
+        self.send(self.client.head(url)).await
     }
 }
 
 #[cfg(test)]
 mod fetch_tests {
-    use wiremock::{matchers::any, Mock, MockServer, ResponseTemplate};
+    use wiremock::{
+        matchers::{any, method},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use crate::fetch::{Fetch, HttpFetch};
 
@@ -44,8 +176,47 @@ mod fetch_tests {
             .mount(&mock_server)
             .await;
 
-        let response = f.get(&mock_server.uri()).await;
+        let response = f.get(&mock_server.uri()).await.unwrap();
+
+        assert_eq!(response.text(), "Hello".to_owned());
+        assert_eq!(response.status, 200);
+        assert_eq!(response.size(), 5);
+    }
+
+    #[tokio::test]
+    async fn get_returns_status_and_content_type() {
+        let f: HttpFetch = Fetch::new();
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(404).set_body_raw("<p>Gone</p>", "text/html"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let response = f.get(&mock_server.uri()).await.unwrap();
+
+        assert_eq!(response.status, 404);
+        assert_eq!(response.reason, "Not Found".to_owned());
+        assert_eq!(response.content_type(), Some("text/html"));
+    }
+
+    #[tokio::test]
+    async fn head_requests_the_status_without_a_body() {
+        let f: HttpFetch = Fetch::new();
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Hello"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let response = f.head(&mock_server.uri()).await.unwrap();
 
-        assert_eq!(response.unwrap(), "Hello".to_owned());
+        assert_eq!(response.status, 200);
+        assert_eq!(response.size(), 0);
     }
 }
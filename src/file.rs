@@ -0,0 +1,174 @@
+use crate::fetch::{Fetch, Response};
+use reqwest::{Error, IntoUrl};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use url::Url;
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") | Some("htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js") | Some("mjs") => "text/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+// Serves file:// URLs from the local file system. The URL path is resolved against root,
+// so a static site build can be crawled as file:/// with root-relative links intact.
+#[derive(Clone)]
+pub struct FileFetch {
+    root: PathBuf,
+}
+
+impl FileFetch {
+    pub fn with_root(root: impl Into<PathBuf>) -> FileFetch {
+        FileFetch { root: root.into() }
+    }
+
+    // Maps /path/ to /path/index.html, the same way a static file server would
+    pub fn path(&self, url: &str) -> Option<PathBuf> {
+        self.resolve(url).map(|(_, path)| path)
+    }
+
+    // The file and the URL it is served at. A directory requested without its trailing
+    // slash is served at /path/, as a server redirecting there would, so that relative
+    // links in its index.html resolve within the directory.
+    fn resolve(&self, url: &str) -> Option<(Url, PathBuf)> {
+        let mut url = Url::parse(url).ok()?;
+
+        if url.scheme() != "file" {
+            return None;
+        }
+
+        let relative = url.to_file_path().ok()?;
+        let mut path = self
+            .root
+            .join(relative.strip_prefix("/").unwrap_or(&relative));
+
+        if !url.path().ends_with('/') && path.is_dir() {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        if url.path().ends_with('/') {
+            path.push("index.html");
+        }
+
+        Some((url, path))
+    }
+}
+
+impl Fetch for FileFetch {
+    fn new() -> FileFetch {
+        FileFetch::with_root("/")
+    }
+
+    async fn get<T: IntoUrl + AsRef<str> + Send>(&self, url: T) -> Result<Response, Error> {
+        let url = url.as_ref();
+        let start = Instant::now();
+
+        let Some((served_url, path)) = self.resolve(url) else {
+            return Ok(Response::not_found(url));
+        };
+
+        let (status, reason, body) = match tokio::fs::read(&path).await {
+            Ok(body) => (200, "OK", body),
+            Err(e) if e.kind() == ErrorKind::NotFound => (404, "Not Found", vec![]),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => (403, "Forbidden", vec![]),
+            Err(_) => (500, "Internal Server Error", vec![]),
+        };
+
+        let headers = if status == 200 {
+            vec![("content-type".to_owned(), content_type(&path).to_owned())]
+        } else {
+            vec![]
+        };
+
+        Ok(Response {
+            requested_url: if served_url.as_str() == url {
+                String::new()
+            } else {
+                url.to_owned()
+            },
+            url: served_url.to_string(),
+            version: "HTTP/1.1".to_owned(),
+            status,
+            reason: reason.to_owned(),
+            headers,
+            body,
+            elapsed: start.elapsed(),
+            ..Response::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::FileFetch;
+    use crate::fetch::Fetch;
+    use std::fs;
+    use uuid::Uuid;
+
+    fn site() -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("site-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("about")).unwrap();
+        fs::write(root.join("index.html"), "<a href=\"/about/\">About</a>").unwrap();
+        fs::write(root.join("about/index.html"), "About").unwrap();
+        fs::write(root.join("style.css"), "p {}").unwrap();
+        root
+    }
+
+    #[tokio::test]
+    async fn file_fetch_maps_directories_to_index_html() {
+        let root = site();
+        let fetch = FileFetch::with_root(&root);
+
+        let home = fetch.get("file:///").await.unwrap();
+        let about = fetch.get("file:///about/").await.unwrap();
+        let about_without_slash = fetch.get("file:///about").await.unwrap();
+
+        fs::remove_dir_all(root).unwrap();
+
+        assert_eq!(home.status, 200);
+        assert_eq!(home.content_type(), Some("text/html"));
+        assert_eq!(home.text(), "<a href=\"/about/\">About</a>");
+        assert_eq!(about.text(), "About");
+        assert_eq!(about_without_slash.text(), "About");
+        assert_eq!(about_without_slash.url, "file:///about/");
+        assert_eq!(about_without_slash.requested(), "file:///about");
+    }
+
+    #[tokio::test]
+    async fn file_fetch_reports_missing_files_as_404() {
+        let root = site();
+        let fetch = FileFetch::with_root(&root);
+
+        let missing = fetch.get("file:///missing.html").await.unwrap();
+        let css = fetch.get("file:///style.css").await.unwrap();
+
+        fs::remove_dir_all(root).unwrap();
+
+        assert_eq!(missing.status, 404);
+        assert_eq!(css.content_type(), Some("text/css"));
+    }
+
+    #[tokio::test]
+    async fn file_fetch_reports_non_file_urls_as_404() {
+        let fetch: FileFetch = Fetch::new();
+
+        let response = fetch.get("https://example.com/").await.unwrap();
+
+        assert_eq!(response.status, 404);
+    }
+}
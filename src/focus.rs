@@ -0,0 +1,219 @@
+use crate::priority_frontier::{UrlInfo, UrlScorer};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+// Terms describing a topic, each with a weight. A term of several words is a phrase, found
+// when its words appear in a row.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TopicProfile {
+    terms: HashMap<String, f64>,
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+impl TopicProfile {
+    pub fn new(terms: impl IntoIterator<Item = (String, f64)>) -> Self {
+        TopicProfile {
+            terms: terms
+                .into_iter()
+                .map(|(term, weight)| (words(&term).collect::<Vec<_>>().join(" "), weight))
+                .collect(),
+        }
+    }
+
+    pub fn from_keywords(keywords: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        TopicProfile::new(
+            keywords
+                .into_iter()
+                .map(|keyword| (keyword.as_ref().to_owned(), 1.0)),
+        )
+    }
+
+    // One "term weight" pair per line, lines starting with # are ignored
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let mut terms = vec![];
+
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (term, weight) = line
+                .rsplit_once(char::is_whitespace)
+                .and_then(|(term, weight)| Some((term.trim(), weight.parse::<f64>().ok()?)))
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidData, format!("invalid term: {}", line))
+                })?;
+            terms.push((term.to_owned(), weight));
+        }
+
+        Ok(TopicProfile::new(terms))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // Share of the profile's total weight whose terms appear in the text, from 0 to 1
+    pub fn relevance(&self, text: &str) -> f64 {
+        let total = self.terms.values().sum::<f64>();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let words = words(text).collect::<Vec<_>>();
+        let distinct = words.iter().map(String::as_str).collect::<HashSet<_>>();
+        let contains = |term: &str| {
+            if !term.contains(' ') {
+                return distinct.contains(term);
+            }
+            let phrase = term.split(' ').collect::<Vec<_>>();
+            words
+                .windows(phrase.len())
+                .any(|window| window.iter().zip(&phrase).all(|(word, term)| word == term))
+        };
+
+        let found = self
+            .terms
+            .iter()
+            .filter(|(term, _)| contains(term))
+            .fold(0.0, |found, (_, weight)| found + weight);
+
+        found / total
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+struct FocusedUrl {
+    // Relevance of the most relevant page linking to the URL
+    priority: f64,
+    // Irrelevant pages in a row on the way to the URL
+    distance: usize,
+}
+
+// Steers a crawl towards pages about a topic. Links found on relevant pages are crawled
+// first, and links found after more than `max_distance` irrelevant pages in a row are
+// dropped.
+#[derive(Debug)]
+pub struct TopicFocus {
+    profile: TopicProfile,
+    threshold: f64,
+    max_distance: usize,
+    urls: Mutex<HashMap<String, FocusedUrl>>,
+}
+
+impl TopicFocus {
+    pub fn new(profile: TopicProfile, threshold: f64, max_distance: usize) -> Self {
+        TopicFocus {
+            profile,
+            threshold,
+            max_distance,
+            urls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn relevance(&self, text: &str) -> f64 {
+        self.profile.relevance(text)
+    }
+
+    // Whether a link found on a page with the given relevance should be crawled
+    pub fn follow(&self, page: &str, relevance: f64, link: &str) -> bool {
+        let mut urls = self.urls.lock().unwrap();
+        let page = urls.get(page).copied().unwrap_or_default();
+
+        let distance = if relevance >= self.threshold {
+            0
+        } else {
+            page.distance + 1
+        };
+        if distance > self.max_distance {
+            return false;
+        }
+
+        let focused = urls.entry(link.to_owned()).or_insert(FocusedUrl {
+            priority: relevance,
+            distance,
+        });
+        focused.priority = focused.priority.max(relevance);
+        focused.distance = focused.distance.min(distance);
+
+        true
+    }
+
+    pub fn priority(&self, url: &str) -> f64 {
+        let urls = self.urls.lock().unwrap();
+        urls.get(url).map_or(0.0, |focused| focused.priority)
+    }
+}
+
+// Orders the frontier by the relevance of the pages linking to each URL
+pub struct FocusScorer(pub Arc<TopicFocus>);
+
+impl<T: AsRef<str>> UrlScorer<T> for FocusScorer {
+    fn score(&self, url: &T, _info: &UrlInfo) -> f64 {
+        self.0.priority(url.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod focus_tests {
+    use super::{TopicFocus, TopicProfile};
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn topic_profile_scores_weighted_share_of_terms_found() {
+        let profile = TopicProfile::new([("rust".to_owned(), 3.0), ("crawler".to_owned(), 1.0)]);
+
+        assert_eq!(profile.relevance("Writing a web crawler in Rust"), 1.0);
+        assert_eq!(profile.relevance("Rust, trusted"), 0.75);
+        assert_eq!(profile.relevance("Nothing to see"), 0.0);
+    }
+
+    #[test]
+    fn topic_profile_matches_terms_of_several_words_as_phrases() {
+        let profile = TopicProfile::new([("Web  Crawler".to_owned(), 1.0)]);
+
+        assert_eq!(profile.relevance("A web crawler, in Rust"), 1.0);
+        assert_eq!(profile.relevance("Crawler for the web"), 0.0);
+        assert_eq!(profile.relevance("web"), 0.0);
+    }
+
+    #[test]
+    fn topic_profile_loads_terms_and_weights_from_file() {
+        let path = std::env::temp_dir().join(format!("profile-{}.txt", Uuid::new_v4()));
+        fs::write(&path, "# topic\nrust 2\n\nweb crawler 0.5\n").unwrap();
+
+        let profile = TopicProfile::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            profile,
+            TopicProfile::new([("rust".to_owned(), 2.0), ("web crawler".to_owned(), 0.5)])
+        );
+    }
+
+    #[test]
+    fn topic_focus_prunes_links_after_too_many_irrelevant_pages() {
+        let focus = TopicFocus::new(TopicProfile::from_keywords(["rust"]), 0.5, 1);
+
+        assert!(focus.follow("/", 1.0, "/rust"));
+        assert!(focus.follow("/", 1.0, "/other"));
+        assert!(focus.follow("/other", 0.0, "/other/deeper"));
+        assert!(!focus.follow("/other/deeper", 0.0, "/other/deeper/still"));
+        assert!(focus.follow("/rust", 1.0, "/rust/more"));
+
+        assert_eq!(focus.priority("/rust/more"), 1.0);
+        assert_eq!(focus.priority("/other/deeper"), 0.0);
+    }
+}
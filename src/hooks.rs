@@ -0,0 +1,91 @@
+use crate::fetch::Response;
+
+// Callbacks around every step of the crawl loop, for logging, metrics or persistence
+// of its own. Every callback does nothing unless implemented.
+pub trait CrawlHooks {
+    // A URL taken from the frontier is about to be requested
+    fn on_request(&mut self, _url: &str) {}
+
+    fn on_response(&mut self, _response: &Response) {}
+
+    // Every link found on the page at `url`, whether it is followed or not
+    fn on_links(&mut self, _url: &str, _links: &[String]) {}
+
+    // Requests that failed, and responses that could not be archived or written
+    fn on_error(&mut self, _url: &str, _error: &str) {}
+
+    // Once every worker is done
+    fn on_finish(&mut self) {}
+}
+
+#[cfg(test)]
+mod hooks_tests {
+    use super::CrawlHooks;
+    use crate::{crawler::Crawler, fetch::Response, replay::ReplayFetch};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[derive(Clone, Default)]
+    struct Calls(Arc<Mutex<Vec<String>>>);
+
+    impl CrawlHooks for Calls {
+        fn on_request(&mut self, url: &str) {
+            self.0.lock().unwrap().push(format!("request {}", url));
+        }
+
+        fn on_response(&mut self, response: &Response) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("response {} {}", response.url, response.status));
+        }
+
+        fn on_links(&mut self, url: &str, links: &[String]) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("links {} {:?}", url, links));
+        }
+
+        fn on_finish(&mut self) {
+            self.0.lock().unwrap().push("finish".to_owned());
+        }
+    }
+
+    #[tokio::test]
+    async fn crawl_hooks_are_called_for_every_page() {
+        let fetch = ReplayFetch::from_responses([Response {
+            url: "https://a.com/".to_owned(),
+            status: 200,
+            body: b"<a href=\"/gone\"></a>".to_vec(),
+            ..Response::default()
+        }]);
+        let calls = Calls::default();
+
+        Crawler::builder()
+            .seed("https://a.com/")
+            .delay(Duration::ZERO)
+            .hooks(calls.clone())
+            .fetch(fetch)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *calls.0.lock().unwrap(),
+            vec![
+                "request https://a.com/",
+                "response https://a.com/ 200",
+                "links https://a.com/ [\"https://a.com/gone\"]",
+                "request https://a.com/gone",
+                "response https://a.com/gone 404",
+                "links https://a.com/gone []",
+                "finish",
+            ]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{
+    crawl_db::CrawlDb,
+    dependencies::DepsConcrete,
+    fetch::Fetch,
+    parser::Parser,
+    url::{filter_url_in, Scope},
+};
+use log::{info, warn};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use url::Url;
+
+pub type Sitemap = BTreeMap<String, Option<String>>;
+
+// The /sitemap.xml of the seed's site, None when there is none
+pub async fn fetch_sitemap_xml<F: Fetch>(client: &F, seed: &str) -> Option<String> {
+    let sitemap_url = Url::parse(seed)
+        .and_then(|url| url.join("/sitemap.xml"))
+        .ok()?;
+
+    match client.get(sitemap_url.as_str()).await {
+        Ok(response) if response.status == 200 => Some(response.text()),
+        Ok(response) => {
+            info!("No sitemap at {} ({})", sitemap_url, response.status);
+            None
+        }
+        Err(e) => {
+            warn!("Error requesting sitemap {} - {:?}", sitemap_url, e);
+            None
+        }
+    }
+}
+
+pub async fn fetch_sitemap<F: Fetch>(client: &F, seed: &str) -> Sitemap {
+    match fetch_sitemap_xml(client, seed).await {
+        Some(xml) => Parser::new(xml).sitemap().into_iter().collect(),
+        None => Sitemap::new(),
+    }
+}
+
+// URLs to re-crawl, pages that are new to the sitemap or whose lastmod changed go first
+pub fn recrawl_order(previous: &CrawlDb, sitemap: &Sitemap) -> Vec<String> {
+    let mut order = vec![];
+    let mut seen = previous.seeds().into_iter().collect::<HashSet<_>>();
+
+    let changed = sitemap
+        .iter()
+        .filter(|(loc, lastmod)| previous.sitemap.get(*loc) != Some(*lastmod))
+        .map(|(loc, _)| loc);
+    let known = previous.visited().map(|(url, _)| url);
+
+    for url in changed.chain(known) {
+        if seen.insert(url.clone()) {
+            order.push(url.clone());
+        }
+    }
+
+    order
+}
+
+// URLs enqueued here are enqueued once, like the links the crawl finds
+async fn seen(deps: &DepsConcrete, url: &str) {
+    if let Some(seen) = &deps.seen {
+        seen.write().await.insert(url);
+    }
+}
+
+// Enqueues everything known from the previous crawl, keeping each page's depth and referrer.
+// The sitemap may list URLs the crawl's scopes leave out, those are skipped.
+pub async fn seed_from_previous(
+    deps: &DepsConcrete,
+    previous: &CrawlDb,
+    sitemap: &Sitemap,
+    scopes: &[Scope],
+) {
+    let mut url_frontier = deps.url_frontier.write().await;
+    let mut data_store = deps.data_store.write().await;
+
+    for url in recrawl_order(previous, sitemap) {
+        if !matches!(filter_url_in(url.clone(), scopes), Ok(Some(_))) {
+            info!("Skipping out of scope URL: {}", url);
+            continue;
+        }
+        if let Some(entry) = previous.pages.get(&url) {
+            if let Some(referrer) = &entry.referrer {
+                data_store.discovered(url.clone(), referrer.clone(), entry.depth);
+            }
+        }
+        seen(deps, &url).await;
+        url_frontier.enqueue(url);
+    }
+}
+
+// Enqueues the URLs a saved crawl found but never requested, the data store is expected
+// to hold that crawl's pages already
+pub async fn seed_from_checkpoint(deps: &DepsConcrete, checkpoint: &CrawlDb) {
+    let pending = checkpoint.pending();
+    info!("Resuming with {} pending URL(s)", pending.len());
+
+    let mut url_frontier = deps.url_frontier.write().await;
+    for url in pending {
+        seen(deps, &url).await;
+        url_frontier.enqueue(url);
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub struct Changeset {
+    pub new: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+fn is_gone(status: u16) -> bool {
+    status == 404 || status == 410
+}
+
+impl Changeset {
+    pub fn between(previous: &CrawlDb, current: &CrawlDb) -> Self {
+        let mut changeset = Changeset::default();
+
+        for (url, entry) in current.visited() {
+            let current_page = entry.page.clone().unwrap_or_default();
+            let previous_page = previous
+                .pages
+                .get(url)
+                .filter(|entry| entry.visited)
+                .map(|entry| entry.page.clone().unwrap_or_default());
+
+            match previous_page {
+                None => changeset.new.push(url.clone()),
+                Some(_) if is_gone(current_page.status) => changeset.removed.push(url.clone()),
+                Some(previous_page)
+                    if previous_page.status != current_page.status
+                        || previous_page.content_hash != current_page.content_hash =>
+                {
+                    changeset.changed.push(url.clone())
+                }
+                Some(_) => changeset.unchanged.push(url.clone()),
+            }
+        }
+
+        for (url, _) in previous.visited() {
+            if !current.pages.get(url).is_some_and(|entry| entry.visited) {
+                changeset.removed.push(url.clone());
+            }
+        }
+        changeset.removed.sort();
+
+        changeset
+    }
+}
+
+impl Display for Changeset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let sections = [
+            ("New", &self.new),
+            ("Removed", &self.removed),
+            ("Changed", &self.changed),
+            ("Unchanged", &self.unchanged),
+        ];
+
+        for (name, urls) in sections {
+            writeln!(f, "{} ({}):", name, urls.len())?;
+            for url in urls {
+                writeln!(f, "  {}", url)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod incremental_tests {
+    use super::{recrawl_order, seed_from_previous, Changeset, Sitemap};
+    use crate::{
+        crawl_db::CrawlDb,
+        data_store::{DataStoreEntry, Page},
+        dependencies::{data_store, url_frontier, Dependencies, UrlFrontierOptions},
+        url::scope,
+    };
+
+    fn page(status: u16, content_hash: &str) -> DataStoreEntry<String> {
+        DataStoreEntry {
+            visited: true,
+            page: Some(Page {
+                status,
+                content_hash: content_hash.to_owned(),
+                ..Page::default()
+            }),
+            ..DataStoreEntry::default()
+        }
+    }
+
+    fn db(pages: Vec<(&str, DataStoreEntry<String>)>) -> CrawlDb {
+        CrawlDb {
+            seed: "/".to_owned(),
+            pages: pages
+                .into_iter()
+                .map(|(url, entry)| (url.to_owned(), entry))
+                .collect(),
+            ..CrawlDb::default()
+        }
+    }
+
+    #[test]
+    fn recrawl_order_puts_pages_with_changed_lastmod_first() {
+        let mut previous = db(vec![
+            ("/", page(200, "1")),
+            ("/a", page(200, "1")),
+            ("/b", page(200, "1")),
+        ]);
+        previous.sitemap = Sitemap::from([
+            ("/a".to_owned(), Some("2024-01-01".to_owned())),
+            ("/b".to_owned(), Some("2024-01-01".to_owned())),
+        ]);
+        let sitemap = Sitemap::from([
+            ("/a".to_owned(), Some("2024-01-01".to_owned())),
+            ("/b".to_owned(), Some("2024-02-01".to_owned())),
+            ("/c".to_owned(), None),
+        ]);
+
+        let order = recrawl_order(&previous, &sitemap);
+
+        assert_eq!(order, vec!["/b", "/c", "/a"]);
+    }
+
+    #[tokio::test]
+    async fn seed_from_previous_skips_sitemap_urls_out_of_scope() {
+        // The frontier starts out with the seed, like it does in a crawl
+        let deps = Dependencies::new()
+            .url_frontier(url_frontier(UrlFrontierOptions {
+                uri: "https://a.com/".to_owned(),
+                ..UrlFrontierOptions::default()
+            }))
+            .data_store(data_store())
+            .build();
+        let previous = CrawlDb {
+            seed: "https://a.com/".to_owned(),
+            ..db(vec![
+                ("https://a.com/", page(200, "1")),
+                ("https://a.com/old", page(200, "1")),
+            ])
+        };
+        let sitemap = Sitemap::from([
+            ("https://a.com/new".to_owned(), None),
+            ("https://b.com/".to_owned(), None),
+            ("mailto:a@a.com".to_owned(), None),
+        ]);
+
+        seed_from_previous(&deps, &previous, &sitemap, &[scope("https://a.com/")]).await;
+
+        let mut url_frontier = deps.url_frontier.write().await;
+        let mut enqueued = vec![];
+        while let Some(url) = url_frontier.dequeue().await {
+            enqueued.push(url);
+        }
+        assert_eq!(
+            enqueued,
+            vec!["https://a.com/", "https://a.com/new", "https://a.com/old"]
+        );
+    }
+
+    #[test]
+    fn changeset_classifies_new_removed_changed_and_unchanged_pages() {
+        let previous = db(vec![
+            ("/", page(200, "1")),
+            ("/changed", page(200, "1")),
+            ("/gone", page(200, "1")),
+            ("/unreachable", page(200, "1")),
+        ]);
+        let current = db(vec![
+            ("/", page(200, "1")),
+            ("/changed", page(200, "2")),
+            ("/gone", page(404, "1")),
+            ("/new", page(200, "1")),
+        ]);
+
+        let changeset = Changeset::between(&previous, &current);
+
+        assert_eq!(
+            changeset,
+            Changeset {
+                new: vec!["/new".to_owned()],
+                removed: vec!["/gone".to_owned(), "/unreachable".to_owned()],
+                changed: vec!["/changed".to_owned()],
+                unchanged: vec!["/".to_owned()],
+            }
+        );
+    }
+}
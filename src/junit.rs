@@ -0,0 +1,111 @@
+use crate::report::LinkCheck;
+use std::collections::BTreeMap;
+
+pub(crate) fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// One test suite per source page and one test case per link checked on it, so CI shows
+// broken links grouped by the page they need fixing on
+pub fn junit_xml(links: &[LinkCheck]) -> String {
+    let mut pages: BTreeMap<&str, Vec<&LinkCheck>> = BTreeMap::new();
+    for link in links {
+        pages.entry(&link.source).or_default().push(link);
+    }
+
+    let failures = links.iter().filter(|link| link.is_broken()).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"links\" tests=\"{}\" failures=\"{}\">\n",
+        links.len(),
+        failures
+    ));
+
+    for (source, links) in pages {
+        let failures = links.iter().filter(|link| link.is_broken()).count();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            escape(source),
+            links.len(),
+            failures
+        ));
+
+        for link in links {
+            let name = escape(&link.target);
+            let classname = escape(source);
+
+            if !link.is_broken() {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                    name, classname
+                ));
+                continue;
+            }
+
+            let message = match &link.error {
+                Some(error) => error.clone(),
+                None => format!("HTTP {}", link.status),
+            };
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\">\n      <failure message=\"{}\">{} -> {}</failure>\n    </testcase>\n",
+                name,
+                classname,
+                escape(&message),
+                escape(source),
+                name
+            ));
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod junit_tests {
+    use super::junit_xml;
+    use crate::report::LinkCheck;
+
+    fn link(source: &str, target: &str, status: u16) -> LinkCheck {
+        LinkCheck {
+            source: source.to_owned(),
+            target: target.to_owned(),
+            status,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn junit_xml_groups_link_checks_by_source_page() {
+        let links = vec![
+            link("/", "/a", 200),
+            link("/", "/b?x=1&y=2", 404),
+            link("/a", "/", 200),
+        ];
+
+        let xml = junit_xml(&links);
+
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"links\" tests=\"3\" failures=\"1\">\n  \
+             <testsuite name=\"/\" tests=\"2\" failures=\"1\">\n    \
+             <testcase name=\"/a\" classname=\"/\"/>\n    \
+             <testcase name=\"/b?x=1&amp;y=2\" classname=\"/\">\n      \
+             <failure message=\"HTTP 404\">/ -> /b?x=1&amp;y=2</failure>\n    \
+             </testcase>\n  \
+             </testsuite>\n  \
+             <testsuite name=\"/a\" tests=\"1\" failures=\"0\">\n    \
+             <testcase name=\"/\" classname=\"/a\"/>\n  \
+             </testsuite>\n\
+             </testsuites>\n"
+        );
+    }
+}
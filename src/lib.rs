@@ -1,7 +1,34 @@
+pub mod cache;
+pub mod config;
+pub mod crawl_db;
 pub mod crawler;
 pub mod data_store;
 pub mod dependencies;
+pub mod diff;
+pub mod events;
+pub mod export;
+pub mod external;
 pub mod fetch;
+pub mod file;
+pub mod focus;
+pub mod hooks;
+pub mod incremental;
+pub mod junit;
+pub mod output;
 pub mod parser;
+pub mod priority_frontier;
+pub mod record;
+pub mod replay;
+pub mod report;
+pub mod seeds;
+pub mod seen;
+pub mod site_frontier;
+pub mod spilling_frontier;
+pub mod trap;
 pub mod url;
 pub mod url_frontier;
+pub mod warc;
+
+pub use crawler::{CrawlReport, Crawler, CrawlerBuilder, CrawlerOptions};
+pub use events::{CrawlEvent, CrawlEvents, FilterReason};
+pub use hooks::CrawlHooks;
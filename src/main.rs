@@ -1,88 +1,856 @@
-use clap::Parser as ClapParser;
+use clap::{
+    builder::{PossibleValue, PossibleValuesParser, TypedValueParser},
+    error::ErrorKind,
+    ArgAction, CommandFactory, FromArgMatches, Parser as ClapParser, Subcommand, ValueEnum,
+};
 use env_logger::Env;
 use log::{info, warn};
-use std::{fmt::Debug, hash::Hash, io::Error, sync::Arc};
-use tokio::task::JoinSet;
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufWriter, Error},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+use url::Url;
 use url_crawler::{
-    crawler::{crawl, crawl_seed},
-    dependencies::{
-        data_store, url_frontier, Dependencies, Deps, DepsConcrete, UrlFrontierOptions,
-    },
-    fetch::{Fetch, HttpFetch},
-    url::url_parts,
+    config::CrawlConfig,
+    crawl_db::CrawlDb,
+    crawler::CrawlerOptions,
+    data_store::Store,
+    diff::diff,
+    export::{links_csv, pages_jsonl, sitemap_xml},
+    fetch::Fetch,
+    file::FileFetch,
+    focus::{TopicFocus, TopicProfile},
+    incremental::Changeset,
+    junit::junit_xml,
+    output::JsonLinesWriter,
+    priority_frontier::FrontierOrder,
+    record::RecordDir,
+    replay::ReplayFetch,
+    report::{checked_links, click_depth, site_summaries, url_inventory},
+    seeds::load_seeds,
+    site_frontier::SiteOptions,
+    trap::TrapOptions,
+    url::QueryRules,
+    warc::WarcWriter,
+    Crawler, CrawlerBuilder,
 };
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Crawl --url, --seeds or --seeds-file
+    #[command(args_override_self = true)]
+    Crawl(CrawlArgs),
+
+    /// Carry on with a crawl saved with --db, requesting the URLs it found but did not get to
+    #[command(args_override_self = true)]
+    Resume {
+        /// Crawl DB to resume, saved back to unless --db is given
+        checkpoint: String,
+
+        #[command(flatten)]
+        args: CrawlArgs,
+    },
+
+    /// Print the report of a crawl saved with --db
+    Report {
+        /// Crawl DB to report on
+        db: String,
+
+        /// Pages deeper than this number of clicks from the seed are flagged in the report
+        #[arg(long, default_value_t = 4)]
+        depth_threshold: usize,
+    },
+
+    /// Export a crawl saved with --db
+    Export {
+        /// Crawl DB to export
+        db: String,
+
+        /// What to export
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Pages)]
+        format: ExportFormat,
+
+        /// Write the export to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Compare two crawls saved with --db
+    Diff {
+        /// Crawl DB of the older crawl
+        old: String,
+
+        /// Crawl DB of the newer crawl
+        new: String,
+
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Crawl, verify every external link once and exit with 1 if any link is broken
+    #[command(args_override_self = true)]
+    Check {
+        /// Write the link checks as JUnit XML, one test suite per source page
+        #[arg(long)]
+        junit: Option<String>,
+
+        #[command(flatten)]
+        args: CrawlArgs,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum ExportFormat {
+    /// One JSON record per crawled page (JSON Lines)
+    Pages,
+    /// Every checked link as CSV
+    Links,
+    /// Every checked link as JUnit XML, one test suite per source page
+    Junit,
+    /// Pages that answered 200 as an XML sitemap
+    Sitemap,
+}
+
+// The same range as fetch.bloom in a config file
+fn false_positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate < 1.0 => Ok(rate),
+        Ok(rate) => Err(format!("must be between 0 and 1, not {}", rate)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// The values of --frontier, the crawl core has FrontierOrder parse itself
+fn frontier_order() -> impl TypedValueParser<Value = FrontierOrder> {
+    let orders =
+        FrontierOrder::ALL.map(|order| PossibleValue::new(order.name()).help(order.description()));
+    PossibleValuesParser::new(orders).map(|name| name.parse::<FrontierOrder>().unwrap())
+}
+
+// Exit codes: 0 when everything went fine, 1 when check found broken links, 2 on errors,
+// e.g. when a seed could not be requested at all
+const EXIT_BROKEN_LINKS: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
 #[derive(ClapParser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// URL to crawl
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+impl Command {
+    // The options of the commands that crawl, which are the ones a config file applies to
+    fn crawl_args(&self) -> Option<&CrawlArgs> {
+        match self {
+            Command::Crawl(args) | Command::Resume { args, .. } | Command::Check { args, .. } => {
+                Some(args)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct CrawlArgs {
+    /// Read options from a TOML crawl job, flags given on the command line take precedence
+    #[arg(long)]
+    config: Option<String>,
+
+    /// URL to crawl, or a local directory (e.g. a static site build) to crawl as file:///
     #[arg(short, long)]
-    url: String,
+    url: Option<String>,
+
+    /// Comma separated URLs to crawl as well, each within its own scope, or - to read
+    /// them from stdin one per line
+    #[arg(long, value_delimiter = ',', action = ArgAction::Set)]
+    seeds: Vec<String>,
+
+    /// File listing URLs to crawl, one per line, each within its own scope
+    #[arg(long)]
+    seeds_file: Option<String>,
+
+    /// Remember enqueued URLs in a Bloom filter with this false positive rate (e.g. 0.001)
+    /// instead of looking them up in the data store, or remembering them exactly when
+    /// crawling several sites. Links that are not followed then get no entry of their own,
+    /// which keeps crawls of millions of URLs compact
+    #[arg(long, value_parser = false_positive_rate)]
+    bloom: Option<f64>,
+
+    /// Number of URLs the Bloom filter holds before it grows
+    #[arg(long, default_value_t = CrawlerOptions::default().bloom_capacity)]
+    bloom_capacity: usize,
 
     /// Number of worker threads
-    #[arg(short, long, default_value_t = 1)]
+    #[arg(short, long, default_value_t = CrawlerOptions::default().workers)]
     workers_n: u8,
 
-    /// Politeness delay (in seconds) between requests
-    #[arg(short, long, default_value_t = 2)]
+    /// Politeness delay (in seconds) between requests, to each site when crawling several
+    #[arg(short, long, default_value_t = CrawlerOptions::default().delay.as_secs())]
     delay: u64,
 
+    /// Stop requesting pages from a site after this many, for every seed
+    #[arg(long)]
+    max_pages: Option<usize>,
+
+    /// Order in which discovered URLs are crawled
+    #[arg(long, value_parser = frontier_order(), default_value_t = FrontierOrder::Fifo)]
+    frontier: FrontierOrder,
+
+    /// Spill the fifo frontier to segment files in this directory once it outgrows memory
+    #[arg(long)]
+    spill_dir: Option<String>,
+
+    /// Number of URLs the spilling frontier keeps in memory before writing a segment
+    #[arg(long, default_value_t = CrawlerOptions::default().frontier_buffer)]
+    frontier_buffer: usize,
+
+    /// Comma separated keywords used by --frontier keywords and focused
+    #[arg(long, value_delimiter = ',', action = ArgAction::Set)]
+    keywords: Vec<String>,
+
+    /// Topic profile for --frontier focused, one "term weight" pair per line
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Pages whose relevance to the topic (0 to 1) reaches this value are relevant
+    #[arg(long, default_value_t = 0.5)]
+    relevance_threshold: f64,
+
+    /// Stop following links after this many irrelevant pages in a row
+    #[arg(long, default_value_t = 1)]
+    max_irrelevant: usize,
+
     /// Print data store at the end of the crawl (boolean value)
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_print")]
     print: bool,
+
+    /// Don't print the data store, e.g. to turn off print set in a config file
+    #[arg(long, overrides_with = "print")]
+    no_print: bool,
+
+    /// Strip utm_*, gclid, fbclid and other tracking parameters, and jsessionid-style session IDs
+    #[arg(long, overrides_with = "no_strip_tracking")]
+    strip_tracking: bool,
+
+    /// Keep tracking parameters, e.g. to turn off strip_tracking set in a config file
+    #[arg(long, overrides_with = "strip_tracking")]
+    no_strip_tracking: bool,
+
+    /// Comma separated query parameters to strip from links, a trailing * matches a prefix
+    #[arg(long, value_delimiter = ',', action = ArgAction::Set)]
+    strip_params: Vec<String>,
+
+    /// Comma separated query parameters to keep in links, every other one is stripped
+    #[arg(long, value_delimiter = ',', action = ArgAction::Set)]
+    keep_params: Option<Vec<String>>,
+
+    /// Comma separated query parameters that don't make a link a different page
+    #[arg(long, value_delimiter = ',', action = ArgAction::Set)]
+    ignore_params: Vec<String>,
+
+    /// Skip links that look like crawler traps: longer than 2048 characters, repeating a path
+    /// segment more than 3 times or a path with more than 100 query strings. The --max-* trap
+    /// flags change these limits, and enable only their own heuristic without --traps.
+    #[arg(long, overrides_with = "no_traps")]
+    traps: bool,
+
+    /// Don't skip crawler traps, e.g. to turn off traps set in a config file. The --max-* trap
+    /// flags still enable their own heuristic.
+    #[arg(long, overrides_with = "traps")]
+    no_traps: bool,
+
+    /// Skip links longer than this many characters as crawler traps
+    #[arg(long)]
+    max_url_length: Option<usize>,
+
+    /// Skip links whose path repeats the same segment more than this many times
+    #[arg(long)]
+    max_repeated_segments: Option<usize>,
+
+    /// Skip links to a path once it was found with this many different query strings
+    #[arg(long)]
+    max_query_variants: Option<usize>,
+
+    /// Skip links to a directory once this many of its pages were found
+    #[arg(long)]
+    max_pages_per_directory: Option<usize>,
+
+    /// Print the click depth report at the end of the crawl (boolean value)
+    #[arg(short, long, overrides_with = "no_report")]
+    report: bool,
+
+    /// Don't print the click depth report, e.g. to turn off report set in a config file
+    #[arg(long, overrides_with = "report")]
+    no_report: bool,
+
+    /// Pages deeper than this number of clicks from the seed are flagged in the report
+    #[arg(long, default_value_t = 4)]
+    depth_threshold: usize,
+
+    /// Write one JSON record per fetched page to this file (JSON Lines)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Archive every request/response pair to gzipped WARC files with this path prefix
+    #[arg(long)]
+    warc: Option<String>,
+
+    /// Start a new WARC file once the current one reaches this size (in bytes)
+    #[arg(long, default_value_t = 1_000_000_000)]
+    warc_max_size: u64,
+
+    /// Record every response to this directory so the crawl can be replayed later
+    #[arg(long, conflicts_with = "warc")]
+    record: Option<String>,
+
+    /// Replay responses from a record directory, or from WARC files (any file or the prefix of
+    /// a rotated set) instead of using the network
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Cache responses in this directory and revalidate them with conditional requests
+    #[arg(long)]
+    cache: Option<String>,
+
+    /// Save the crawl (data store and sitemap) to this file so later crawls can build on it
+    #[arg(long)]
+    db: Option<String>,
+
+    /// Re-crawl incrementally using a crawl saved with --db, and print what changed since
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Verify links to other sites with one request each, without crawling them. Skipped with --replay
+    #[arg(long, overrides_with = "no_external_links")]
+    external_links: bool,
+
+    /// Don't verify links to other sites, e.g. to turn off external_links set in a config file
+    #[arg(long, overrides_with = "external_links")]
+    no_external_links: bool,
+
+    /// Write the changeset of an incremental crawl to this file as JSON
+    #[arg(long, requires = "since")]
+    changeset: Option<String>,
+}
+
+// Crawls and prints what the crawl found, and what changed since `previous` if given
+async fn execute<F: Fetch + Clone + Send + Sync + 'static>(
+    crawler: CrawlerBuilder<F>,
+    args: CrawlArgs,
+    previous: Option<CrawlDb>,
+) -> Result<CrawlDb, Error> {
+    let CrawlArgs {
+        report,
+        depth_threshold,
+        changeset,
+        ..
+    } = args;
+
+    let current = crawler.build()?.run().await?.db;
+    if !current.traps.is_empty() {
+        warn!("{} link(s) skipped as crawler traps", current.traps.len());
+    }
+    if let Some(frontier) = &current.frontier {
+        info!("Frontier: {}", frontier);
+    }
+
+    if report {
+        print_report(&current, depth_threshold);
+    } else if !current.sites.is_empty() {
+        for summary in site_summaries(&current) {
+            println!("{}", summary);
+        }
+    }
+
+    if let Some(previous) = &previous {
+        let changes = Changeset::between(previous, &current);
+        println!("{}", changes);
+
+        if let Some(path) = changeset {
+            fs::write(path, serde_json::to_vec_pretty(&changes)?)?;
+        }
+    }
+
+    // Nothing was crawled, which is an error rather than a site without broken links
+    if let Some((seed, error)) = current.seed_error() {
+        return Err(Error::other(format!(
+            "seed {} could not be requested: {}",
+            seed, error
+        )));
+    }
+
+    Ok(current)
 }
 
-async fn execute<T: Clone + Hash + Eq, U>(args: Args, deps: Deps<T, U>) -> Result<(), Error> {
-    let Args { url, workers_n, .. } = args;
+// Printed after a crawl with --report, and by the report command from a saved crawl
+fn print_report(db: &CrawlDb, depth_threshold: usize) {
+    let seeds = db.seeds();
+    let data_store = Store::from_entries(db.pages.clone());
+
+    for seed in &seeds {
+        if seeds.len() > 1 {
+            println!("Click depth from {}:", seed);
+        }
+        println!("{}", click_depth(&data_store, seed, depth_threshold));
+    }
+
+    for (kind, urls) in url_inventory(&data_store) {
+        println!("{} link(s) to {}", urls.len(), kind);
+        if !kind.is_fetchable() {
+            for url in urls {
+                println!("  {}", url);
+            }
+        }
+    }
+
+    println!("Crawler traps ({}):", db.traps.len());
+    for trip in &db.traps {
+        println!("  {}", trip);
+    }
 
-    let original_url_parts = Arc::new(url_parts(&url));
+    if let Some(frontier) = &db.frontier {
+        println!("Frontier: {}", frontier);
+    }
 
-    // first thread attempts to crawl the seed url
-    let client: HttpFetch = Fetch::new();
-    crawl_seed(deps.clone(), client, original_url_parts.clone()).await?;
+    if seeds.len() > 1 || !db.sites.is_empty() {
+        for summary in site_summaries(db) {
+            println!("{}", summary);
+        }
+    }
 
-    let mut tasks = JoinSet::new();
+    let links = checked_links(db);
+    let broken = links.iter().filter(|link| link.is_broken()).count();
+    println!("{} link(s) checked, {} broken", links.len(), broken);
+}
+
+// A frontier holding the seed of `options`, in the order given by --frontier.
+// `sitemap` is the seed's sitemap.xml, for --frontier sitemap.
+fn execute_check(current: &CrawlDb, junit: Option<String>) -> Result<usize, Error> {
+    let links = checked_links(current);
+    let broken = links
+        .iter()
+        .filter(|link| link.is_broken())
+        .collect::<Vec<_>>();
+
+    for link in &broken {
+        println!("Broken link: {}", link);
+    }
+    println!("{} link(s) checked, {} broken", links.len(), broken.len());
+
+    if let Some(path) = junit {
+        fs::write(path, junit_xml(&links))?;
+    }
+
+    Ok(broken.len())
+}
+
+fn execute_report(db: &str, depth_threshold: usize) -> Result<(), Error> {
+    print_report(&CrawlDb::load(db)?, depth_threshold);
+    Ok(())
+}
 
-    for _n in 0..workers_n {
-        let client: HttpFetch = Fetch::new(); // each worker gets a HTTP client
-        let task = tokio::spawn(crawl(deps.clone(), client, original_url_parts.clone()));
+fn execute_export(db: &str, format: &ExportFormat, output: Option<&str>) -> Result<(), Error> {
+    let db = CrawlDb::load(db)?;
+    let exported = match format {
+        ExportFormat::Pages => pages_jsonl(&db)?,
+        ExportFormat::Links => links_csv(&checked_links(&db)),
+        ExportFormat::Junit => junit_xml(&checked_links(&db)),
+        ExportFormat::Sitemap => sitemap_xml(&db),
+    };
 
-        tasks.spawn(task);
+    match output {
+        Some(path) => fs::write(path, exported),
+        None => {
+            print!("{}", exported);
+            Ok(())
+        }
     }
+}
+
+fn execute_diff(old: &str, new: &str, json: bool) -> Result<(), Error> {
+    let changes = diff(&CrawlDb::load(old)?, &CrawlDb::load(new)?);
 
-    while let Some(_res) = tasks.join_next().await {
-        info!("Worker completed");
+    if json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else {
+        println!("{}", changes);
     }
 
     Ok(())
 }
 
+fn exit_code(result: Result<(), Error>) -> ExitCode {
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            warn!("There's been an error: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     // If RUST_LOG env is not set, fallback to printing all logs at info-level or above
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    let cli_args = Args::parse();
+    // The command line is parsed as is to find the config file, then again after its options
+    // so that the flags given take precedence
+    let argv = std::env::args().collect::<Vec<_>>();
+    let matches = Cli::command().get_matches_from(&argv);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = match cli
+        .command
+        .crawl_args()
+        .and_then(|args| args.config.as_ref())
+    {
+        Some(path) => match CrawlConfig::load(path) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Invalid config {}", e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        None => None,
+    };
+    let cli = match (&config, cli.command.crawl_args(), matches.subcommand_name()) {
+        (Some(config), Some(args), Some(command)) => {
+            // Seeds on the command line replace the ones of the config file
+            let mut config_args =
+                if args.url.is_none() && args.seeds.is_empty() && args.seeds_file.is_none() {
+                    config.seed_args()
+                } else {
+                    vec![]
+                };
+            config_args.extend(config.to_args());
 
-    info!("Initialising with seed url: {}", cli_args.url);
+            // Its options go right after the name of the command, which comes first as the
+            // CLI has no flags of its own
+            let at = argv
+                .iter()
+                .position(|arg| arg == command)
+                .map_or(argv.len(), |n| n + 1);
+            Cli::parse_from(
+                argv[..at]
+                    .iter()
+                    .cloned()
+                    .chain(config_args)
+                    .chain(argv[at..].iter().cloned()),
+            )
+        }
+        _ => cli,
+    };
 
-    let url_frontier = url_frontier(UrlFrontierOptions {
-        delay_s: Some(cli_args.delay),
-        uri: cli_args.url.clone(),
+    match cli.command {
+        Command::Crawl(args) => run_crawl(args, config, None, None).await,
+        Command::Resume {
+            checkpoint,
+            mut args,
+        } => {
+            let resumed = match CrawlDb::load(&checkpoint) {
+                Ok(resumed) => resumed,
+                Err(e) => {
+                    warn!("Unable to read crawl to resume {}: {}", checkpoint, e);
+                    return ExitCode::from(EXIT_ERROR);
+                }
+            };
+            args.db.get_or_insert(checkpoint);
+            run_crawl(args, config, Some(resumed), None).await
+        }
+        Command::Report {
+            db,
+            depth_threshold,
+        } => exit_code(execute_report(&db, depth_threshold)),
+        Command::Export { db, format, output } => {
+            exit_code(execute_export(&db, &format, output.as_deref()))
+        }
+        Command::Diff { old, new, json } => exit_code(execute_diff(&old, &new, json)),
+        Command::Check { junit, args } => run_crawl(args, config, None, Some(junit)).await,
+    }
+}
+
+// Crawls the seeds of `cli_args`, after those of the crawl it resumes if any. `junit` is
+// given when checking links.
+async fn run_crawl(
+    mut cli_args: CrawlArgs,
+    config: Option<CrawlConfig>,
+    resumed: Option<CrawlDb>,
+    junit: Option<Option<String>>,
+) -> ExitCode {
+    let mut seeds = resumed.iter().flat_map(CrawlDb::seeds).collect::<Vec<_>>();
+    seeds.extend(cli_args.url.clone());
+    for seed in &cli_args.seeds {
+        if seed != "-" {
+            seeds.push(seed.clone());
+            continue;
+        }
+        match load_seeds("-") {
+            Ok(stdin_seeds) => seeds.extend(stdin_seeds),
+            Err(e) => {
+                warn!("Unable to read seeds from stdin: {}", e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+    if let Some(path) = &cli_args.seeds_file {
+        match load_seeds(path) {
+            Ok(file_seeds) => seeds.extend(file_seeds),
+            Err(e) => {
+                warn!("Unable to read seeds file {}: {}", path, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    let mut strip_params = cli_args.strip_params.clone();
+    if cli_args.strip_tracking {
+        strip_params.extend(QueryRules::preset().strip);
+    }
+    let query_rules = QueryRules::new(
+        strip_params,
+        cli_args.keep_params.clone(),
+        cli_args.ignore_params.clone(),
+    );
+    // Seeds follow the query rules like every link found, local directories aside
+    for seed in &mut seeds {
+        if Url::parse(seed).is_ok() {
+            *seed = query_rules.apply(seed.clone());
+        }
+    }
+
+    let mut unique = HashSet::new();
+    seeds.retain(|seed| unique.insert(seed.clone()));
+
+    if seeds.is_empty() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--url, --seeds or --seeds-file is required to crawl",
+            )
+            .exit();
+    }
+    // A local directory is crawled as file:/// with the directory as the root
+    let mut file_root = None;
+    if let [seed] = &mut seeds[..] {
+        if Path::new(seed).is_dir() {
+            match Path::new(seed).canonicalize() {
+                Ok(root) => file_root = Some(root),
+                Err(e) => {
+                    warn!("Unable to read directory {}: {}", seed, e);
+                    return ExitCode::from(EXIT_ERROR);
+                }
+            }
+            *seed = "file:///".to_owned();
+        }
+    }
+
+    // Every seed is fetched with the same client, and FileFetch has a single root
+    let local = seeds[0].starts_with("file:");
+    if local && resumed.is_some() {
+        warn!("A crawl of local files can't be resumed, crawl the directory again instead");
+        return ExitCode::from(EXIT_ERROR);
+    }
+    if seeds.len() > 1
+        && seeds
+            .iter()
+            .any(|seed| seed.starts_with("file:") || Path::new(seed).is_dir())
+    {
+        warn!("Local files and directories can only be crawled on their own");
+        return ExitCode::from(EXIT_ERROR);
+    }
+
+    for seed in &seeds {
+        info!("Initialising with seed url: {}", seed);
+    }
+
+    let previous = match &cli_args.since {
+        Some(path) => match CrawlDb::load(path) {
+            Ok(previous) => Some(previous),
+            Err(e) => {
+                warn!("Unable to read previous crawl {}: {}", path, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        None => None,
+    };
+
+    // Incremental and resumed crawls revalidate through the cache of the crawl they build on,
+    // other crawls only cache with --cache
+    if cli_args.cache.is_none() {
+        cli_args.cache = previous
+            .iter()
+            .chain(&resumed)
+            .find_map(|db| db.cache.clone());
+    }
+
+    let check = junit.is_some();
+    let external_links = check || cli_args.external_links;
+
+    // Options of a [[site]] in the config file win over the ones for every site
+    let site_options = seeds
+        .iter()
+        .filter_map(|seed| {
+            let site = config.as_ref()?.site(seed)?;
+            let options = SiteOptions {
+                delay: Duration::from_secs(site.delay.unwrap_or(cli_args.delay)),
+                max_pages: site.max_pages.or(cli_args.max_pages),
+            };
+            Some((seed.clone(), options))
+        })
+        .collect::<Vec<_>>();
+
+    let mut crawler = Crawler::builder()
+        .seeds(seeds)
+        .workers(cli_args.workers_n)
+        .delay(Duration::from_secs(cli_args.delay))
+        .query_rules(query_rules)
+        .frontier(cli_args.frontier)
+        .keywords(cli_args.keywords.clone());
+    for (seed, options) in site_options {
+        crawler = crawler.site(seed, options);
+    }
+    if let Some(max_pages) = cli_args.max_pages {
+        crawler = crawler.max_pages(max_pages);
+    }
+
+    if let FrontierOrder::Focused = cli_args.frontier {
+        let profile = match &cli_args.profile {
+            Some(path) => match TopicProfile::load(path) {
+                Ok(profile) => profile,
+                Err(e) => {
+                    warn!("Unable to read topic profile {}: {}", path, e);
+                    return ExitCode::from(EXIT_ERROR);
+                }
+            },
+            None => TopicProfile::from_keywords(&cli_args.keywords),
+        };
+        if profile.is_empty() {
+            warn!("A focused crawl needs --keywords or --profile");
+            return ExitCode::from(EXIT_ERROR);
+        }
+
+        crawler = crawler.focus(TopicFocus::new(
+            profile,
+            cli_args.relevance_threshold,
+            cli_args.max_irrelevant,
+        ));
+    }
+
+    if let Some(dir) = &cli_args.spill_dir {
+        crawler = crawler.spill_dir(dir, cli_args.frontier_buffer);
+    }
+
+    let defaults = if cli_args.traps {
+        TrapOptions::recommended()
+    } else {
+        TrapOptions::default()
+    };
+    crawler = crawler.traps(TrapOptions {
+        max_url_length: cli_args.max_url_length.or(defaults.max_url_length),
+        max_repeated_segments: cli_args
+            .max_repeated_segments
+            .or(defaults.max_repeated_segments),
+        max_query_variants: cli_args.max_query_variants.or(defaults.max_query_variants),
+        max_pages_per_directory: cli_args
+            .max_pages_per_directory
+            .or(defaults.max_pages_per_directory),
     });
-    let data_store = data_store();
 
-    let deps = Dependencies::new()
-        .url_frontier(url_frontier)
-        .data_store(data_store)
-        .build();
+    if let Some(fp_rate) = cli_args.bloom {
+        crawler = crawler.bloom(fp_rate, cli_args.bloom_capacity);
+    }
+
+    if let Some(path) = &cli_args.output {
+        match File::create(path) {
+            Ok(file) => crawler = crawler.output(JsonLinesWriter::new(BufWriter::new(file))),
+            Err(e) => {
+                warn!("Unable to create output file {}: {}", path, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    if let Some(prefix) = &cli_args.warc {
+        crawler = crawler.archive(WarcWriter::new(prefix, cli_args.warc_max_size));
+    }
+
+    if let Some(path) = &cli_args.record {
+        match RecordDir::new(path) {
+            Ok(archive) => crawler = crawler.archive(archive),
+            Err(e) => {
+                warn!("Unable to create record directory {}: {}", path, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    if let Some(previous) = &previous {
+        crawler = crawler.since(previous.clone());
+    }
+    if let Some(resumed) = resumed {
+        crawler = crawler.resume(resumed);
+    }
+    if let Some(path) = &cli_args.db {
+        crawler = crawler.db(path);
+    }
+
+    let replay = match &cli_args.replay {
+        Some(path) => match ReplayFetch::from_path(path) {
+            Ok(replay) => {
+                info!("Replaying {} responses from {}", replay.len(), path);
+                Some(replay)
+            }
+            Err(e) => {
+                warn!("Unable to read recorded responses {}: {}", path, e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        None => None,
+    };
+
+    // External links are checked over the network, unless the crawl is replayed offline
+    if external_links && replay.is_some() {
+        info!("External links are not checked when replaying");
+    } else {
+        crawler = crawler.external_links(external_links);
+    }
+
+    let result = match replay {
+        Some(replay) => execute(crawler.fetch(replay), cli_args, previous).await,
+        None if local => {
+            let client = FileFetch::with_root(file_root.unwrap_or_else(|| PathBuf::from("/")));
+            execute(crawler.fetch(client), cli_args, previous).await
+        }
+        None => match cli_args.cache.clone() {
+            Some(path) => match crawler.cache(path) {
+                Ok(crawler) => execute(crawler, cli_args, previous).await,
+                Err(e) => Err(e),
+            },
+            None => execute(crawler, cli_args, previous).await,
+        },
+    };
+
+    let result = result.and_then(|current| match junit {
+        Some(junit) => execute_check(&current, junit),
+        None => Ok(0),
+    });
 
-    match execute(cli_args, deps).await {
-        Ok(_) => {
+    match result {
+        Ok(0) => {
             info!("Done");
+            ExitCode::SUCCESS
         }
+        Ok(_) => ExitCode::from(EXIT_BROKEN_LINKS),
         Err(e) => {
-            warn!("There's been an error: {}", e)
+            warn!("There's been an error: {}", e);
+            ExitCode::from(EXIT_ERROR)
         }
     }
 }
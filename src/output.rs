@@ -0,0 +1,95 @@
+use crate::{fetch::Response, parser::PageMetadata};
+use serde::Serialize;
+use std::io::{Result, Write};
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct PageRecord {
+    pub url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub size: usize,
+    pub elapsed_ms: u128,
+    pub unchanged: bool,
+    pub depth: usize,
+    pub referrer: Option<String>,
+    pub outlinks: Vec<String>,
+    pub metadata: PageMetadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<f64>,
+    // The URL as found on the referring page, when query parameter rules rewrote it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+}
+
+pub trait PageWriter {
+    fn write(&mut self, record: &PageRecord) -> Result<()>;
+}
+
+pub trait ResponseWriter {
+    fn write(&mut self, response: &Response) -> Result<()>;
+}
+
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesWriter { writer }
+    }
+}
+
+impl<W: Write> PageWriter for JsonLinesWriter<W> {
+    // Flushed after every record so that results of an interrupted crawl are not lost
+    fn write(&mut self, record: &PageRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod output_tests {
+    use super::{JsonLinesWriter, PageRecord, PageWriter};
+    use crate::parser::PageMetadata;
+
+    fn record(url: &str) -> PageRecord {
+        PageRecord {
+            url: url.to_owned(),
+            status: 200,
+            content_type: Some("text/html".to_owned()),
+            size: 10,
+            elapsed_ms: 5,
+            unchanged: false,
+            depth: 1,
+            referrer: Some("https://example.com/".to_owned()),
+            outlinks: vec!["https://example.com/b".to_owned()],
+            metadata: PageMetadata {
+                title: Some("A".to_owned()),
+                ..PageMetadata::default()
+            },
+            relevance: None,
+            original: None,
+        }
+    }
+
+    #[test]
+    fn json_lines_writer_writes_one_record_per_line() {
+        let mut writer = JsonLinesWriter::new(vec![]);
+
+        writer.write(&record("https://example.com/a")).unwrap();
+        writer.write(&record("https://example.com/b")).unwrap();
+
+        let output = String::from_utf8(writer.writer).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "{\"url\":\"https://example.com/a\",\"status\":200,\"content_type\":\"text/html\",\
+            \"size\":10,\"elapsed_ms\":5,\"unchanged\":false,\"depth\":1,\"referrer\":\"https://example.com/\",\
+            \"outlinks\":[\"https://example.com/b\"],\
+            \"metadata\":{\"title\":\"A\",\"description\":null,\"canonical\":null}}"
+        );
+    }
+}
@@ -1,4 +1,12 @@
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub canonical: Option<String>,
+}
 
 pub struct Parser {
     html_parsed: Html,
@@ -11,7 +19,7 @@ impl Parser {
         }
     }
 
-    pub fn all_links(self) -> Vec<String> {
+    pub fn all_links(&self) -> Vec<String> {
         let mut vec: Vec<String> = vec![];
         let selector = Selector::parse("a").unwrap();
 
@@ -24,11 +32,99 @@ impl Parser {
 
         vec
     }
+
+    // Visible text of the document, without the contents of <script> and <style>
+    pub fn text(&self) -> String {
+        let mut text = vec![];
+
+        for node in self.html_parsed.root_element().descendants() {
+            let Some(value) = node.value().as_text() else {
+                continue;
+            };
+            let hidden = node
+                .parent()
+                .and_then(|parent| parent.value().as_element().map(|element| element.name()))
+                .is_some_and(|name| matches!(name, "script" | "style" | "noscript"));
+
+            if !hidden && !value.trim().is_empty() {
+                text.push(value.trim());
+            }
+        }
+
+        text.join(" ")
+    }
+
+    // Returns <loc> and <lastmod> of every <url> in a sitemap.xml document
+    pub fn sitemap(&self) -> Vec<(String, Option<String>)> {
+        let url = Selector::parse("url").unwrap();
+        let loc = Selector::parse("loc").unwrap();
+        let lastmod = Selector::parse("lastmod").unwrap();
+        let text =
+            |element: scraper::ElementRef| element.text().collect::<String>().trim().to_owned();
+
+        self.html_parsed
+            .select(&url)
+            .filter_map(|element| {
+                let loc = element.select(&loc).next().map(text)?;
+                let lastmod = element.select(&lastmod).next().map(text);
+                Some((loc, lastmod))
+            })
+            .collect()
+    }
+
+    // Returns <loc> and <priority> of every <url> in a sitemap.xml document, a missing or
+    // invalid priority defaults to 0.5 as in the sitemaps protocol
+    pub fn sitemap_priorities(&self) -> Vec<(String, f64)> {
+        let url = Selector::parse("url").unwrap();
+        let loc = Selector::parse("loc").unwrap();
+        let priority = Selector::parse("priority").unwrap();
+        let text =
+            |element: scraper::ElementRef| element.text().collect::<String>().trim().to_owned();
+
+        self.html_parsed
+            .select(&url)
+            .filter_map(|element| {
+                let loc = element.select(&loc).next().map(text)?;
+                let priority = element
+                    .select(&priority)
+                    .next()
+                    .and_then(|element| text(element).parse().ok())
+                    .unwrap_or(0.5);
+                Some((loc, priority))
+            })
+            .collect()
+    }
+
+    pub fn metadata(&self) -> PageMetadata {
+        let title = Selector::parse("title").unwrap();
+        let description = Selector::parse("meta[name=\"description\"]").unwrap();
+        let canonical = Selector::parse("link[rel=\"canonical\"]").unwrap();
+
+        PageMetadata {
+            title: self
+                .html_parsed
+                .select(&title)
+                .next()
+                .map(|element| element.text().collect::<String>().trim().to_owned()),
+            description: self
+                .html_parsed
+                .select(&description)
+                .next()
+                .and_then(|element| element.value().attr("content"))
+                .map(|content| content.to_owned()),
+            canonical: self
+                .html_parsed
+                .select(&canonical)
+                .next()
+                .and_then(|element| element.value().attr("href"))
+                .map(|href| href.to_owned()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod parser_tests {
-    use super::Parser;
+    use super::{PageMetadata, Parser};
 
     #[test]
     fn all_links_extracts_existing_links_from_html_string() {
@@ -43,4 +139,56 @@ mod parser_tests {
         assert_eq!(links.pop(), Some("/link".to_owned()));
         assert_eq!(links.pop(), None);
     }
+
+    #[test]
+    fn text_skips_scripts_and_styles() {
+        let parser = Parser::new(
+            "<head><title>Home</title><style>p {}</style></head>\
+            <body><h1>Rust</h1><script>let x = 1;</script><p>Web <b>crawler</b></p></body>"
+                .to_owned(),
+        );
+
+        assert_eq!(parser.text(), "Home Rust Web crawler");
+    }
+
+    #[test]
+    fn metadata_extracts_title_description_and_canonical() {
+        let parser = Parser::new(
+            "<head><title> Home </title><meta name=\"description\" content=\"Welcome\">\
+            <link rel=\"canonical\" href=\"https://example.com/\"></head><body></body>"
+                .to_owned(),
+        );
+
+        assert_eq!(
+            parser.metadata(),
+            PageMetadata {
+                title: Some("Home".to_owned()),
+                description: Some("Welcome".to_owned()),
+                canonical: Some("https://example.com/".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn sitemap_extracts_locations_and_last_modified_dates() {
+        let parser = Parser::new(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\
+            <url><loc>https://example.com/</loc><lastmod>2024-01-01</lastmod></url>\
+            <url><loc> https://example.com/about </loc></url>\
+            </urlset>"
+                .to_owned(),
+        );
+
+        assert_eq!(
+            parser.sitemap(),
+            vec![
+                (
+                    "https://example.com/".to_owned(),
+                    Some("2024-01-01".to_owned())
+                ),
+                ("https://example.com/about".to_owned(), None),
+            ]
+        );
+    }
 }
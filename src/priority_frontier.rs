@@ -0,0 +1,413 @@
+use crate::{
+    parser::Parser,
+    url_frontier::{Dequeue, Enqueue, Queue},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::Hash,
+    str::FromStr,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+// The orders of --frontier, and fetch.frontier in a config file, by their lowercase name
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontierOrder {
+    Fifo,
+    Bfs,
+    Dfs,
+    Inlinks,
+    Keywords,
+    Focused,
+    Sitemap,
+}
+
+impl FrontierOrder {
+    pub const ALL: [FrontierOrder; 7] = [
+        FrontierOrder::Fifo,
+        FrontierOrder::Bfs,
+        FrontierOrder::Dfs,
+        FrontierOrder::Inlinks,
+        FrontierOrder::Keywords,
+        FrontierOrder::Focused,
+        FrontierOrder::Sitemap,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FrontierOrder::Fifo => "fifo",
+            FrontierOrder::Bfs => "bfs",
+            FrontierOrder::Dfs => "dfs",
+            FrontierOrder::Inlinks => "inlinks",
+            FrontierOrder::Keywords => "keywords",
+            FrontierOrder::Focused => "focused",
+            FrontierOrder::Sitemap => "sitemap",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FrontierOrder::Fifo => "First in, first out",
+            FrontierOrder::Bfs => "Shallowest pages first",
+            FrontierOrder::Dfs => "Deepest pages first",
+            FrontierOrder::Inlinks => "Most linked to pages first",
+            FrontierOrder::Keywords => "URLs containing the most --keywords first",
+            FrontierOrder::Focused => {
+                "Links from pages most relevant to --keywords or --profile first, see --max-irrelevant"
+            }
+            FrontierOrder::Sitemap => {
+                "URLs with the highest <priority> in the seed's sitemap.xml first"
+            }
+        }
+    }
+}
+
+impl FromStr for FrontierOrder {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        FrontierOrder::ALL
+            .into_iter()
+            .find(|order| order.name() == name)
+            .ok_or_else(|| format!("unknown frontier order {:?}", name))
+    }
+}
+
+impl Display for FrontierOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name())
+    }
+}
+
+// What the frontier knows about a pending URL when it is scored
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct UrlInfo {
+    // Clicks from the seed, one more than the URL being crawled when this one was found
+    pub depth: usize,
+    // Position in which the URL was first enqueued
+    pub order: u64,
+    // How many times the URL was enqueued, i.e. links to it found so far
+    pub inlinks: usize,
+}
+
+// Higher scores are dequeued first, ties are broken by enqueue order
+pub trait UrlScorer<T> {
+    fn score(&self, url: &T, info: &UrlInfo) -> f64;
+}
+
+impl<T, F: Fn(&T, &UrlInfo) -> f64> UrlScorer<T> for F {
+    fn score(&self, url: &T, info: &UrlInfo) -> f64 {
+        self(url, info)
+    }
+}
+
+// Shallow pages first
+pub struct BreadthFirst;
+
+impl<T> UrlScorer<T> for BreadthFirst {
+    fn score(&self, _url: &T, info: &UrlInfo) -> f64 {
+        -(info.depth as f64)
+    }
+}
+
+// Deep pages first
+pub struct DepthFirst;
+
+impl<T> UrlScorer<T> for DepthFirst {
+    fn score(&self, _url: &T, info: &UrlInfo) -> f64 {
+        info.depth as f64
+    }
+}
+
+// Pages linked to most often first
+pub struct InlinkCount;
+
+impl<T> UrlScorer<T> for InlinkCount {
+    fn score(&self, _url: &T, info: &UrlInfo) -> f64 {
+        info.inlinks as f64
+    }
+}
+
+// <priority> from sitemap.xml, URLs missing from the sitemap get the default of 0.5
+pub struct SitemapPriority {
+    priorities: HashMap<String, f64>,
+}
+
+impl SitemapPriority {
+    pub fn new(priorities: impl IntoIterator<Item = (String, f64)>) -> Self {
+        SitemapPriority {
+            priorities: priorities.into_iter().collect(),
+        }
+    }
+
+    pub fn from_sitemap(xml: String) -> Self {
+        SitemapPriority::new(Parser::new(xml).sitemap_priorities())
+    }
+}
+
+impl<T: AsRef<str>> UrlScorer<T> for SitemapPriority {
+    fn score(&self, url: &T, _info: &UrlInfo) -> f64 {
+        *self.priorities.get(url.as_ref()).unwrap_or(&0.5)
+    }
+}
+
+// Number of keywords that appear in the URL, ignoring case
+pub struct KeywordRelevance {
+    keywords: Vec<String>,
+}
+
+impl KeywordRelevance {
+    pub fn new(keywords: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        KeywordRelevance {
+            keywords: keywords
+                .into_iter()
+                .map(|keyword| keyword.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl<T: AsRef<str>> UrlScorer<T> for KeywordRelevance {
+    fn score(&self, url: &T, _info: &UrlInfo) -> f64 {
+        let url = url.as_ref().to_lowercase();
+
+        self.keywords
+            .iter()
+            .filter(|keyword| url.contains(keyword.as_str()))
+            .count() as f64
+    }
+}
+
+struct Scored<T> {
+    score: f64,
+    order: u64,
+    version: u64,
+    url: T,
+}
+
+impl<T> PartialEq for Scored<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Scored<T> {}
+
+impl<T> PartialOrd for Scored<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// BinaryHeap is a max-heap: highest score first, then lowest order
+impl<T> Ord for Scored<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.order.cmp(&self.order))
+            .then_with(|| self.version.cmp(&other.version))
+    }
+}
+
+// A frontier that hands out the best scored URL first. A URL is pending at most once,
+// enqueueing it again updates its inlinks and score. The crawl loop holds the frontier
+// while a page is processed, so URLs enqueued after a dequeue were found on that page.
+pub struct PriorityFrontier<T> {
+    heap: BinaryHeap<Scored<T>>,
+    pending: HashMap<T, (UrlInfo, u64)>,
+    scorer: Box<dyn UrlScorer<T> + Send + Sync>,
+    delay_s: Option<u64>,
+    current_depth: Option<usize>,
+    next_order: u64,
+    next_version: u64,
+}
+
+impl<T: Clone + Hash + Eq> PriorityFrontier<T> {
+    pub fn new(scorer: impl UrlScorer<T> + Send + Sync + 'static) -> PriorityFrontier<T> {
+        PriorityFrontier {
+            heap: BinaryHeap::new(),
+            pending: HashMap::new(),
+            scorer: Box::new(scorer),
+            delay_s: None,
+            current_depth: None,
+            next_order: 0,
+            next_version: 0,
+        }
+    }
+
+    pub fn delay_s(self, delay_s: u64) -> PriorityFrontier<T> {
+        PriorityFrontier {
+            delay_s: (delay_s > 0).then_some(delay_s),
+            ..self
+        }
+    }
+
+    pub fn value(mut self, value: T) -> PriorityFrontier<T> {
+        self.enqueue(value);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl<T: Clone + Hash + Eq + Send> Queue<T> for PriorityFrontier<T> {}
+
+#[async_trait]
+impl<T: Clone + Hash + Eq + Send> Dequeue<T> for PriorityFrontier<T> {
+    async fn dequeue(&mut self) -> Option<T> {
+        if let Some(delay_s) = self.delay_s {
+            sleep(Duration::from_secs(delay_s)).await;
+        }
+
+        // Entries superseded by a later enqueue of the same URL are skipped
+        while let Some(scored) = self.heap.pop() {
+            if let Some((info, version)) = self.pending.get(&scored.url) {
+                if *version == scored.version {
+                    self.current_depth = Some(info.depth);
+                    self.pending.remove(&scored.url);
+                    return Some(scored.url);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<T: Clone + Hash + Eq> Enqueue<T> for PriorityFrontier<T> {
+    fn enqueue(&mut self, value: T) {
+        let version = self.next_version;
+        self.next_version += 1;
+
+        let (info, _) = self.pending.entry(value.clone()).or_insert_with(|| {
+            let info = UrlInfo {
+                depth: self.current_depth.map_or(0, |depth| depth + 1),
+                order: self.next_order,
+                inlinks: 0,
+            };
+            self.next_order += 1;
+            (info, version)
+        });
+        info.inlinks += 1;
+        let info = info.clone();
+
+        self.heap.push(Scored {
+            score: self.scorer.score(&value, &info),
+            order: info.order,
+            version,
+            url: value.clone(),
+        });
+        self.pending.insert(value, (info, version));
+    }
+}
+
+#[cfg(test)]
+mod priority_frontier_tests {
+    use super::{
+        BreadthFirst, DepthFirst, FrontierOrder, InlinkCount, KeywordRelevance, PriorityFrontier,
+        SitemapPriority, UrlInfo,
+    };
+    use crate::url_frontier::{Dequeue, Enqueue};
+
+    // Crawls a small tree: the seed links to /a and /b, /a links to /a/1
+    async fn crawl_order(mut frontier: PriorityFrontier<String>) -> Vec<String> {
+        let links = |url: &str| match url {
+            "/" => vec!["/a", "/b"],
+            "/a" => vec!["/a/1"],
+            _ => vec![],
+        };
+        let mut order = vec![];
+
+        frontier.enqueue("/".to_owned());
+        while let Some(url) = frontier.dequeue().await {
+            for link in links(&url) {
+                frontier.enqueue(link.to_owned());
+            }
+            order.push(url);
+        }
+
+        order
+    }
+
+    #[tokio::test]
+    async fn priority_frontier_crawls_breadth_first() {
+        let order = crawl_order(PriorityFrontier::new(BreadthFirst)).await;
+
+        assert_eq!(order, vec!["/", "/a", "/b", "/a/1"]);
+    }
+
+    #[tokio::test]
+    async fn priority_frontier_crawls_depth_first() {
+        let order = crawl_order(PriorityFrontier::new(DepthFirst)).await;
+
+        assert_eq!(order, vec!["/", "/a", "/a/1", "/b"]);
+    }
+
+    #[tokio::test]
+    async fn priority_frontier_uses_closures_as_scorers() {
+        let scorer = |url: &String, _info: &UrlInfo| if url == "/b" { 1.0 } else { 0.0 };
+
+        let order = crawl_order(PriorityFrontier::new(scorer)).await;
+
+        assert_eq!(order, vec!["/", "/b", "/a", "/a/1"]);
+    }
+
+    #[tokio::test]
+    async fn priority_frontier_rescores_urls_that_are_enqueued_again() {
+        let mut frontier = PriorityFrontier::new(InlinkCount)
+            .value("/a".to_owned())
+            .value("/b".to_owned())
+            .value("/b".to_owned());
+
+        assert_eq!(frontier.len(), 2);
+        assert_eq!(frontier.dequeue().await, Some("/b".to_owned()));
+        assert_eq!(frontier.dequeue().await, Some("/a".to_owned()));
+        assert_eq!(frontier.dequeue().await, None);
+    }
+
+    #[tokio::test]
+    async fn priority_frontier_orders_by_sitemap_priority_and_keywords() {
+        let sitemap = SitemapPriority::from_sitemap(
+            "<urlset><url><loc>/a</loc><priority>0.2</priority></url>\
+            <url><loc>/b</loc><priority>0.9</priority></url></urlset>"
+                .to_owned(),
+        );
+        let mut by_sitemap = PriorityFrontier::new(sitemap)
+            .value("/a".to_owned())
+            .value("/c".to_owned())
+            .value("/b".to_owned());
+        let mut by_keywords = PriorityFrontier::new(KeywordRelevance::new(["Rust", "crawl"]))
+            .value("/blog/go".to_owned())
+            .value("/blog/rust-crawler".to_owned());
+
+        assert_eq!(by_sitemap.dequeue().await, Some("/b".to_owned()));
+        assert_eq!(by_sitemap.dequeue().await, Some("/c".to_owned()));
+        assert_eq!(by_sitemap.dequeue().await, Some("/a".to_owned()));
+        assert_eq!(
+            by_keywords.dequeue().await,
+            Some("/blog/rust-crawler".to_owned())
+        );
+    }
+
+    #[test]
+    fn frontier_order_parses_its_own_names() {
+        for order in FrontierOrder::ALL {
+            assert_eq!(order.to_string().parse::<FrontierOrder>(), Ok(order));
+        }
+        assert_eq!(
+            "random".parse::<FrontierOrder>(),
+            Err("unknown frontier order \"random\"".to_owned())
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    fetch::{Headers, Response},
+    output::ResponseWriter,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RecordMeta {
+    url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    requested_url: String,
+    version: String,
+    status: u16,
+    reason: String,
+    headers: Headers,
+}
+
+// FNV-1a, used instead of DefaultHasher because hashes are persisted and have to stay
+// the same between builds
+pub fn stable_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn record_key(url: &str) -> String {
+    stable_hash(url.as_bytes())
+}
+
+// A directory of recorded responses, one <key>.json (status line and headers)
+// and one <key>.body (raw body) per URL
+pub struct RecordDir {
+    path: PathBuf,
+}
+
+impl RecordDir {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+        Ok(RecordDir { path })
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = record_key(url);
+        (
+            self.path.join(format!("{}.json", key)),
+            self.path.join(format!("{}.body", key)),
+        )
+    }
+
+    fn read(meta_path: &Path, body_path: &Path) -> Result<Response> {
+        let meta: RecordMeta = serde_json::from_slice(&fs::read(meta_path)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        Ok(Response {
+            url: meta.url,
+            requested_url: meta.requested_url,
+            version: meta.version,
+            status: meta.status,
+            reason: meta.reason,
+            headers: meta.headers,
+            body: fs::read(body_path)?,
+            ..Response::default()
+        })
+    }
+
+    pub fn get(&self, url: &str) -> Result<Option<Response>> {
+        let (meta_path, body_path) = self.paths(url);
+
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+
+        Self::read(&meta_path, &body_path).map(Some)
+    }
+
+    pub fn put(&self, response: &Response) -> Result<()> {
+        // Keyed by the requested URL, which is the one a replay asks for
+        let (meta_path, body_path) = self.paths(response.requested());
+        let meta = RecordMeta {
+            url: response.url.clone(),
+            requested_url: response.requested_url.clone(),
+            version: response.version.clone(),
+            status: response.status,
+            reason: response.reason.clone(),
+            headers: response.headers.clone(),
+        };
+
+        // The body goes first so that a record is never visible without it
+        fs::write(body_path, &response.body)?;
+        fs::write(meta_path, serde_json::to_vec(&meta)?)
+    }
+
+    pub fn all(&self) -> Result<Vec<Response>> {
+        let mut responses = vec![];
+
+        for entry in fs::read_dir(&self.path)? {
+            let meta_path = entry?.path();
+
+            if meta_path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let body_path = meta_path.with_extension("body");
+                responses.push(Self::read(&meta_path, &body_path)?);
+            }
+        }
+
+        Ok(responses)
+    }
+}
+
+impl ResponseWriter for RecordDir {
+    fn write(&mut self, response: &Response) -> Result<()> {
+        self.put(response)
+    }
+}
+
+#[cfg(test)]
+mod record_tests {
+    use super::RecordDir;
+    use crate::fetch::Response;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn record_dir_stores_and_reads_back_responses() {
+        let path = std::env::temp_dir().join(format!("records-{}", Uuid::new_v4()));
+        let records = RecordDir::new(&path).unwrap();
+        let response = Response {
+            url: "https://example.com/".to_owned(),
+            status: 200,
+            headers: vec![("etag".to_owned(), "\"1\"".to_owned())],
+            body: b"Hello".to_vec(),
+            ..Response::default()
+        };
+
+        records.put(&response).unwrap();
+
+        assert_eq!(
+            records.get("https://example.com/").unwrap(),
+            Some(response.clone())
+        );
+        assert_eq!(records.get("https://example.com/missing").unwrap(), None);
+        assert_eq!(records.all().unwrap(), vec![response]);
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    fetch::{Fetch, Response},
+    record::RecordDir,
+    warc::{parse_response, read_records},
+};
+use reqwest::{Error, IntoUrl};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use url::Url;
+
+// Serves previously recorded responses instead of going to the network.
+// URLs that were not recorded are answered with a 404.
+#[derive(Clone, Default)]
+pub struct ReplayFetch {
+    responses: Arc<HashMap<String, Response>>,
+}
+
+// So that https://a.com and https://a.com/ are the same recording
+fn key(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| url.to_owned())
+}
+
+// Every file of the rotated set <prefix>-00000.warc.gz, <prefix>-00001.warc.gz etc. that
+// path is either the prefix of, or one of the files of
+fn warc_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let prefix = match name
+        .strip_suffix(".warc.gz")
+        .and_then(|name| name.rsplit_once('-'))
+    {
+        Some((prefix, index)) if index.len() == 5 && index.bytes().all(|b| b.is_ascii_digit()) => {
+            prefix.to_owned()
+        }
+        _ if path.is_file() => return Ok(vec![path.to_owned()]),
+        _ => name.into_owned(),
+    };
+
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    let mut files = (0..)
+        .map(|index| directory.join(format!("{}-{:05}.warc.gz", prefix, index)))
+        .take_while(|file| file.is_file())
+        .collect::<Vec<_>>();
+
+    if files.is_empty() {
+        // Reported as the file that doesn't exist
+        fs::metadata(path)?;
+        files.push(path.to_owned());
+    }
+
+    Ok(files)
+}
+
+impl ReplayFetch {
+    // Served for the URL that was requested as well as the one it ended up at
+    pub fn from_responses(responses: impl IntoIterator<Item = Response>) -> Self {
+        let mut by_url = HashMap::new();
+
+        for response in responses {
+            by_url
+                .entry(key(&response.url))
+                .or_insert_with(|| response.clone());
+            by_url.insert(key(response.requested()), response);
+        }
+
+        ReplayFetch {
+            responses: Arc::new(by_url),
+        }
+    }
+
+    pub fn from_warc(path: impl AsRef<Path>) -> Result<Self> {
+        let mut responses = vec![];
+
+        for file in warc_files(path.as_ref())? {
+            let records = read_records(file)?;
+            let requests = records
+                .iter()
+                .filter(|record| record.get("WARC-Type") == Some("request"))
+                .filter_map(|record| Some((record.get("WARC-Record-ID")?, record)))
+                .collect::<HashMap<_, _>>();
+
+            for record in &records {
+                let Some(mut response) = parse_response(record) else {
+                    continue;
+                };
+
+                // The request record holds the URL that was requested, before any redirect
+                if let Some(request) = record
+                    .get("WARC-Concurrent-To")
+                    .and_then(|id| requests.get(id))
+                {
+                    let requested_url = request.get("WARC-Target-URI").unwrap_or_default();
+                    if requested_url != response.url {
+                        response.requested_url = requested_url.to_owned();
+                    }
+                }
+
+                responses.push(response);
+            }
+        }
+
+        Ok(Self::from_responses(responses))
+    }
+
+    pub fn from_dir(path: impl AsRef<Path>) -> Result<Self> {
+        let records = RecordDir::new(path.as_ref())?;
+        Ok(Self::from_responses(records.all()?))
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        if path.as_ref().is_dir() {
+            Self::from_dir(path)
+        } else {
+            Self::from_warc(path)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
+
+impl Fetch for ReplayFetch {
+    fn new() -> ReplayFetch {
+        ReplayFetch::default()
+    }
+
+    async fn get<T: IntoUrl + AsRef<str> + Send>(
+        &self,
+        url: T,
+    ) -> std::result::Result<Response, Error> {
+        let url = url.as_ref();
+
+        Ok(self
+            .responses
+            .get(&key(url))
+            .cloned()
+            .unwrap_or_else(|| Response::not_found(url)))
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::ReplayFetch;
+    use crate::{
+        fetch::{Fetch, Response},
+        output::ResponseWriter,
+        record::RecordDir,
+        warc::WarcWriter,
+    };
+    use std::fs;
+    use uuid::Uuid;
+
+    fn response(url: &str) -> Response {
+        Response {
+            url: url.to_owned(),
+            version: "HTTP/1.1".to_owned(),
+            status: 200,
+            reason: "OK".to_owned(),
+            body: b"<a href=\"/b\">B</a>".to_vec(),
+            ..Response::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_fetch_serves_recorded_responses_and_404s_the_rest() {
+        let replay = ReplayFetch::from_responses(vec![response("https://example.com/a")]);
+
+        let found = replay.get("https://example.com/a").await.unwrap();
+        let missing = replay.get("https://example.com/b").await.unwrap();
+
+        assert_eq!(found, response("https://example.com/a"));
+        assert_eq!(missing.status, 404);
+    }
+
+    #[tokio::test]
+    async fn replay_fetch_normalizes_urls_and_serves_redirects_by_requested_url() {
+        let redirected = Response {
+            requested_url: "https://example.com/old".to_owned(),
+            ..response("https://example.com/new")
+        };
+        let replay =
+            ReplayFetch::from_responses(vec![response("https://example.com/"), redirected]);
+
+        assert_eq!(replay.get("https://example.com").await.unwrap().status, 200);
+        assert_eq!(
+            replay.get("https://example.com/old").await.unwrap().url,
+            "https://example.com/new"
+        );
+        assert_eq!(
+            replay.get("https://example.com/new").await.unwrap().status,
+            200
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_fetch_reads_every_file_of_a_rotated_warc_set() {
+        let prefix = std::env::temp_dir().join(format!("warc-{}", Uuid::new_v4()));
+        let mut writer = WarcWriter::new(&prefix, 1);
+        writer.write(&response("https://example.com/a")).unwrap();
+        writer
+            .write(&Response {
+                requested_url: "https://example.com/old".to_owned(),
+                ..response("https://example.com/b")
+            })
+            .unwrap();
+
+        let from_prefix = ReplayFetch::from_path(&prefix).unwrap();
+        let from_file = ReplayFetch::from_path(writer.path(1)).unwrap();
+        fs::remove_file(writer.path(0)).unwrap();
+        fs::remove_file(writer.path(1)).unwrap();
+
+        for replay in [from_prefix, from_file] {
+            assert_eq!(
+                replay.get("https://example.com/a").await.unwrap().status,
+                200
+            );
+            assert_eq!(
+                replay.get("https://example.com/old").await.unwrap().url,
+                "https://example.com/b"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_fetch_reads_responses_from_a_warc_file() {
+        let prefix = std::env::temp_dir().join(format!("warc-{}", Uuid::new_v4()));
+        let mut writer = WarcWriter::new(&prefix, 1024 * 1024);
+        writer.write(&response("https://example.com/a")).unwrap();
+
+        let replay = ReplayFetch::from_path(writer.path(0)).unwrap();
+        fs::remove_file(writer.path(0)).unwrap();
+
+        assert_eq!(replay.len(), 1);
+        assert_eq!(
+            replay.get("https://example.com/a").await.unwrap(),
+            response("https://example.com/a")
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_fetch_reads_responses_from_a_record_directory() {
+        let path = std::env::temp_dir().join(format!("records-{}", Uuid::new_v4()));
+        let mut records = RecordDir::new(&path).unwrap();
+        records.write(&response("https://example.com/a")).unwrap();
+
+        let replay = ReplayFetch::from_path(&path).unwrap();
+        fs::remove_dir_all(path).unwrap();
+
+        assert_eq!(
+            replay.get("https://example.com/a").await.unwrap(),
+            response("https://example.com/a")
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use crate::{
+    crawl_db::CrawlDb,
+    data_store::DataStore,
+    site_frontier::SiteStats,
+    url::{filter_url, scope, url_kind, UrlKind},
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::Hash,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageDepth<T> {
+    pub url: T,
+    pub depth: usize,
+    pub path: Vec<T>,
+    pub too_deep: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ClickDepthReport<T> {
+    pub pages: Vec<PageDepth<T>>,
+    pub histogram: BTreeMap<usize, usize>,
+    pub threshold: usize,
+}
+
+impl<T> ClickDepthReport<T> {
+    pub fn too_deep(&self) -> impl Iterator<Item = &PageDepth<T>> {
+        self.pages.iter().filter(|page| page.too_deep)
+    }
+}
+
+// Breadth-first walk over the stored link graph, starting at the seed.
+// Only URLs that were visited during the crawl are reported.
+pub fn click_depth<T: Clone + Hash + Eq + 'static>(
+    data_store: &(impl DataStore<T, T> + ?Sized),
+    seed: &T,
+    threshold: usize,
+) -> ClickDepthReport<T> {
+    let mut report = ClickDepthReport {
+        pages: vec![],
+        histogram: BTreeMap::new(),
+        threshold,
+    };
+
+    if !data_store.has_visited(seed) {
+        return report;
+    }
+
+    let mut parents: HashMap<T, Option<T>> = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    parents.insert(seed.clone(), None);
+    queue.push_back((seed.clone(), 0));
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if let Some(entry) = data_store.get(&url) {
+            for found in &entry.urls_found {
+                if !parents.contains_key(found) && data_store.has_visited(found) {
+                    parents.insert(found.clone(), Some(url.clone()));
+                    queue.push_back((found.clone(), depth + 1));
+                }
+            }
+        }
+
+        let mut path = vec![url.clone()];
+        while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+            path.push(parent.clone());
+        }
+        path.reverse();
+
+        *report.histogram.entry(depth).or_insert(0) += 1;
+        report.pages.push(PageDepth {
+            url,
+            depth,
+            path,
+            too_deep: depth > threshold,
+        });
+    }
+
+    report
+}
+
+impl<T: Display> Display for ClickDepthReport<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "Click depth report ({} pages)", self.pages.len())?;
+
+        for page in &self.pages {
+            let path = page
+                .path
+                .iter()
+                .map(|url| url.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let flag = if page.too_deep { " [too deep]" } else { "" };
+            writeln!(f, "{} {}{}: {}", page.depth, page.url, flag, path)?;
+        }
+
+        writeln!(f, "Pages by depth:")?;
+        for (depth, count) in &self.histogram {
+            writeln!(f, "{:>4} | {}", depth, count)?;
+        }
+
+        let too_deep = self.too_deep().count();
+        write!(
+            f,
+            "{} page(s) deeper than {} clicks",
+            too_deep, self.threshold
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct LinkCheck {
+    pub source: String,
+    pub target: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LinkCheck {
+    pub fn is_broken(&self) -> bool {
+        self.status >= 400 || self.error.is_some()
+    }
+}
+
+impl Display for LinkCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.error {
+            Some(error) => write!(f, "{} -> {} ({})", self.source, self.target, error),
+            None => write!(f, "{} -> {} ({})", self.source, self.target, self.status),
+        }
+    }
+}
+
+// Links from a crawled page to any page whose status is known, once per source
+pub fn checked_links(db: &CrawlDb) -> Vec<LinkCheck> {
+    let mut links = vec![];
+
+    for (source, entry) in db.visited() {
+        let targets = entry.urls_found.iter().collect::<BTreeSet<_>>();
+
+        for target in targets {
+            let Some(page) = db.pages.get(target).and_then(|entry| entry.page.as_ref()) else {
+                continue;
+            };
+
+            links.push(LinkCheck {
+                source: source.clone(),
+                target: target.clone(),
+                status: page.status,
+                error: page.error.clone(),
+            });
+        }
+    }
+
+    links
+}
+
+// Links to pages that answered with an error status, or could not be requested at all
+pub fn broken_links(db: &CrawlDb) -> Vec<LinkCheck> {
+    checked_links(db)
+        .into_iter()
+        .filter(LinkCheck::is_broken)
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct SiteSummary {
+    pub seed: String,
+    pub pages: usize,
+    pub broken_links: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SiteStats>,
+}
+
+impl Display for SiteSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}: {} page(s) crawled, {} broken link(s)",
+            self.seed, self.pages, self.broken_links
+        )?;
+        if let Some(stats) = &self.stats {
+            write!(f, ", {}", stats)?;
+        }
+        Ok(())
+    }
+}
+
+// Results of every seed of a shared crawl. A page counts for the first seed whose
+// scope it is in, broken links for the site of the page they were found on.
+pub fn site_summaries(db: &CrawlDb) -> Vec<SiteSummary> {
+    let seeds = db.seeds();
+    let scopes = seeds.iter().map(|seed| scope(seed)).collect::<Vec<_>>();
+    let site_of = |url: &str| {
+        scopes
+            .iter()
+            .position(|scope| matches!(filter_url(url.to_owned(), scope.clone()), Ok(Some(_))))
+    };
+
+    let mut summaries = seeds
+        .iter()
+        .map(|seed| SiteSummary {
+            seed: seed.clone(),
+            pages: 0,
+            broken_links: 0,
+            stats: db.sites.get(seed).cloned(),
+        })
+        .collect::<Vec<_>>();
+
+    for (url, _) in db.visited() {
+        if let Some(site) = site_of(url) {
+            summaries[site].pages += 1;
+        }
+    }
+    for link in broken_links(db) {
+        if let Some(site) = site_of(&link.source) {
+            summaries[site].broken_links += 1;
+        }
+    }
+
+    summaries
+}
+
+// Every link found on a crawled page, grouped by kind. Links stored before kinds were
+// recorded are classified again from the URL.
+pub fn url_inventory(
+    data_store: &(impl DataStore<String, String> + ?Sized),
+) -> BTreeMap<UrlKind, BTreeSet<String>> {
+    let mut inventory = BTreeMap::<UrlKind, BTreeSet<String>>::new();
+
+    for key in data_store.keys() {
+        let Some(entry) = data_store.get(&key).filter(|entry| entry.visited) else {
+            continue;
+        };
+
+        for url in &entry.urls_found {
+            let kind = data_store
+                .get(url)
+                .and_then(|found| found.kind)
+                .unwrap_or_else(|| url_kind(url));
+            inventory.entry(kind).or_default().insert(url.clone());
+        }
+    }
+
+    inventory
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::{
+        broken_links, click_depth, site_summaries, url_inventory, LinkCheck, PageDepth, SiteSummary,
+    };
+    use crate::{
+        crawl_db::CrawlDb,
+        data_store::{DataStore, Page, Store},
+        url::UrlKind,
+    };
+
+    fn store() -> Store<String, String> {
+        let mut s = Store::new();
+        s.add("/".to_owned(), Some("/a".to_owned()));
+        s.add("/".to_owned(), Some("/b".to_owned()));
+        s.add("/a".to_owned(), Some("/c".to_owned()));
+        s.add("/b".to_owned(), Some("/c".to_owned()));
+        s.add("/b".to_owned(), Some("http://external.com".to_owned()));
+        s.add("/c".to_owned(), Some("/".to_owned()));
+
+        for key in ["/", "/a", "/b", "/c"] {
+            s.visited(&key.to_owned());
+        }
+        s
+    }
+
+    #[test]
+    fn click_depth_finds_shortest_path_to_every_crawled_page() {
+        let report = click_depth(&store(), &"/".to_owned(), 5);
+
+        assert_eq!(report.pages.len(), 4);
+        assert_eq!(
+            report.pages.last(),
+            Some(&PageDepth {
+                url: "/c".to_owned(),
+                depth: 2,
+                path: vec!["/".to_owned(), "/a".to_owned(), "/c".to_owned()],
+                too_deep: false,
+            })
+        );
+    }
+
+    #[test]
+    fn click_depth_builds_histogram_by_depth() {
+        let report = click_depth(&store(), &"/".to_owned(), 5);
+
+        assert_eq!(report.histogram.get(&0), Some(&1));
+        assert_eq!(report.histogram.get(&1), Some(&2));
+        assert_eq!(report.histogram.get(&2), Some(&1));
+    }
+
+    #[test]
+    fn click_depth_flags_pages_deeper_than_threshold() {
+        let report = click_depth(&store(), &"/".to_owned(), 1);
+
+        let too_deep = report.too_deep().map(|p| p.url.clone()).collect::<Vec<_>>();
+
+        assert_eq!(too_deep, vec!["/c".to_owned()]);
+    }
+
+    #[test]
+    fn click_depth_is_empty_if_seed_was_not_crawled() {
+        let report = click_depth(&store(), &"/missing".to_owned(), 1);
+
+        assert!(report.pages.is_empty());
+    }
+
+    #[test]
+    fn broken_links_lists_links_to_error_pages_once_per_source() {
+        let mut s = store();
+        s.add("/".to_owned(), Some("/c".to_owned()));
+        s.page(
+            &"/c".to_owned(),
+            Page {
+                status: 404,
+                ..Page::default()
+            },
+        );
+
+        let links = broken_links(&CrawlDb::from_store("/", &s));
+
+        assert_eq!(
+            links,
+            vec![
+                LinkCheck {
+                    source: "/".to_owned(),
+                    target: "/c".to_owned(),
+                    status: 404,
+                    error: None
+                },
+                LinkCheck {
+                    source: "/a".to_owned(),
+                    target: "/c".to_owned(),
+                    status: 404,
+                    error: None
+                },
+                LinkCheck {
+                    source: "/b".to_owned(),
+                    target: "/c".to_owned(),
+                    status: 404,
+                    error: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn url_inventory_groups_links_by_kind() {
+        let mut s = Store::new();
+        let links = [
+            ("https://example.com/", "https://example.com/about"),
+            ("https://example.com/", "mailto:me@example.com"),
+            ("https://example.com/about", "https://example.com/logo.png"),
+            ("https://example.com/about", "http://external.com/"),
+            ("https://example.com/about", "https://exa mple.com/"),
+        ];
+        for (page, link) in links {
+            s.add(page.to_owned(), Some(link.to_owned()));
+            s.visited(&page.to_owned());
+        }
+        s.kind("mailto:me@example.com".to_owned(), UrlKind::NonHttp);
+
+        let inventory = url_inventory(&s);
+
+        assert_eq!(
+            inventory[&UrlKind::NonHttp],
+            ["mailto:me@example.com".to_owned()].into()
+        );
+        assert_eq!(
+            inventory[&UrlKind::Asset],
+            ["https://example.com/logo.png".to_owned()].into()
+        );
+        assert_eq!(
+            inventory[&UrlKind::Page],
+            ["http://external.com/", "https://example.com/about"]
+                .map(String::from)
+                .into()
+        );
+        assert_eq!(
+            inventory[&UrlKind::Malformed],
+            ["https://exa mple.com/".to_owned()].into()
+        );
+    }
+
+    #[test]
+    fn site_summaries_count_pages_and_broken_links_per_seed() {
+        let mut s = Store::new();
+        s.add(
+            "https://a.com/".to_owned(),
+            Some("https://a.com/gone".to_owned()),
+        );
+        s.add(
+            "https://b.com/".to_owned(),
+            Some("https://a.com/".to_owned()),
+        );
+        s.add("https://a.com/gone".to_owned(), None);
+        s.page(
+            &"https://a.com/gone".to_owned(),
+            Page {
+                status: 404,
+                ..Page::default()
+            },
+        );
+        for key in ["https://a.com/", "https://a.com/gone", "https://b.com/"] {
+            s.visited(&key.to_owned());
+        }
+        let db = CrawlDb {
+            seeds: vec!["https://a.com/".to_owned(), "https://b.com/".to_owned()],
+            ..CrawlDb::from_store("https://a.com/", &s)
+        };
+
+        assert_eq!(
+            site_summaries(&db),
+            vec![
+                SiteSummary {
+                    seed: "https://a.com/".to_owned(),
+                    pages: 2,
+                    broken_links: 1,
+                    stats: None,
+                },
+                SiteSummary {
+                    seed: "https://b.com/".to_owned(),
+                    pages: 1,
+                    broken_links: 0,
+                    stats: None,
+                },
+            ]
+        );
+    }
+}
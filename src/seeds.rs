@@ -0,0 +1,50 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Result},
+    path::Path,
+};
+
+// One seed URL per line, blank lines and lines starting with # are ignored
+pub fn read_seeds(reader: impl BufRead) -> Result<Vec<String>> {
+    let mut seeds = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        seeds.push(line.to_owned());
+    }
+
+    Ok(seeds)
+}
+
+// "-" reads the seeds from stdin
+pub fn load_seeds(path: impl AsRef<Path>) -> Result<Vec<String>> {
+    if path.as_ref() == Path::new("-") {
+        return read_seeds(io::stdin().lock());
+    }
+
+    read_seeds(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod seeds_tests {
+    use super::read_seeds;
+
+    #[test]
+    fn read_seeds_skips_blank_lines_and_comments() {
+        let input = "# sites\nhttps://example.com/\n\n  https://example.org/blog/  \n";
+
+        let seeds = read_seeds(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            seeds,
+            vec![
+                "https://example.com/".to_owned(),
+                "https://example.org/blog/".to_owned()
+            ]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    f64::consts::LN_2,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+
+// Remembers which URLs were already enqueued, so that links to them are not enqueued
+// again. It is the only dedup of links when set: the data store then holds an entry for
+// enqueued URLs only, links that are not followed are just listed on their page.
+pub trait SeenSet: Debug {
+    // Returns true the first time a URL is inserted
+    fn insert(&mut self, url: &str) -> bool;
+    fn contains(&self, url: &str) -> bool;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Exact, but keeps every URL in memory
+#[derive(Debug, Default)]
+pub struct HashSeenSet {
+    urls: HashSet<String>,
+}
+
+impl SeenSet for HashSeenSet {
+    fn insert(&mut self, url: &str) -> bool {
+        self.urls.insert(url.to_owned())
+    }
+
+    fn contains(&self, url: &str) -> bool {
+        self.urls.contains(url)
+    }
+
+    fn len(&self) -> usize {
+        self.urls.len()
+    }
+}
+
+fn hashes(url: &str) -> (u64, u64) {
+    let mut first = DefaultHasher::new();
+    url.hash(&mut first);
+
+    let mut second = DefaultHasher::new();
+    (url, 0x9e37_79b9_7f4a_7c15_u64).hash(&mut second);
+
+    // An odd step visits different bits for every one of the k probes
+    (first.finish(), second.finish() | 1)
+}
+
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    m: u64,
+    k: u64,
+    capacity: usize,
+    len: usize,
+}
+
+impl BloomFilter {
+    fn new(capacity: usize, fp_rate: f64) -> Self {
+        let m = (-(capacity as f64) * fp_rate.ln() / (LN_2 * LN_2))
+            .ceil()
+            .max(64.0) as u64;
+        let k = (-fp_rate.log2()).ceil().max(1.0) as u64;
+
+        BloomFilter {
+            bits: vec![0; m.div_ceil(64) as usize],
+            m,
+            k,
+            capacity,
+            len: 0,
+        }
+    }
+
+    fn indexes(&self, (first, second): (u64, u64)) -> impl Iterator<Item = u64> + '_ {
+        (0..self.k).map(move |i| first.wrapping_add(i.wrapping_mul(second)) % self.m)
+    }
+
+    fn contains(&self, hashes: (u64, u64)) -> bool {
+        self.indexes(hashes)
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    fn insert(&mut self, hashes: (u64, u64)) {
+        let indexes = self.indexes(hashes).collect::<Vec<_>>();
+        for index in indexes {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+        self.len += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+}
+
+// Scalable Bloom filter (Almeida et al.): once a filter holds its capacity a new one,
+// twice as large and with half the false positive rate, is added. The overall false
+// positive rate stays below the configured one however many URLs are inserted.
+// False positives mean a URL is occasionally never enqueued, there are no false negatives.
+#[derive(Debug)]
+pub struct ScalableBloomFilter {
+    filters: Vec<BloomFilter>,
+    fp_rate: f64,
+    len: usize,
+}
+
+const GROWTH: usize = 2;
+const TIGHTENING: f64 = 0.5;
+
+impl ScalableBloomFilter {
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        let fp_rate = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+
+        ScalableBloomFilter {
+            filters: vec![BloomFilter::new(
+                capacity.max(1),
+                fp_rate * (1.0 - TIGHTENING),
+            )],
+            fp_rate,
+            len: 0,
+        }
+    }
+
+    fn grow(&mut self) {
+        let n = self.filters.len() as i32;
+        let last = &self.filters[self.filters.len() - 1];
+        let fp_rate = self.fp_rate * (1.0 - TIGHTENING) * TIGHTENING.powi(n);
+
+        self.filters
+            .push(BloomFilter::new(last.capacity * GROWTH, fp_rate));
+    }
+}
+
+impl SeenSet for ScalableBloomFilter {
+    fn insert(&mut self, url: &str) -> bool {
+        let hashes = hashes(url);
+        if self.filters.iter().any(|filter| filter.contains(hashes)) {
+            return false;
+        }
+
+        if self.filters.last().is_some_and(BloomFilter::is_full) {
+            self.grow();
+        }
+        if let Some(filter) = self.filters.last_mut() {
+            filter.insert(hashes);
+        }
+        self.len += 1;
+
+        true
+    }
+
+    fn contains(&self, url: &str) -> bool {
+        let hashes = hashes(url);
+        self.filters.iter().any(|filter| filter.contains(hashes))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod seen_tests {
+    use super::{HashSeenSet, ScalableBloomFilter, SeenSet};
+
+    #[test]
+    fn hash_seen_set_inserts_each_url_once() {
+        let mut seen = HashSeenSet::default();
+
+        assert!(seen.insert("https://example.com/"));
+        assert!(!seen.insert("https://example.com/"));
+        assert!(seen.contains("https://example.com/"));
+        assert_eq!(seen.len(), 1);
+    }
+
+    #[test]
+    fn scalable_bloom_filter_has_no_false_negatives_and_grows() {
+        let mut seen = ScalableBloomFilter::new(100, 0.01);
+
+        for n in 0..1_000 {
+            seen.insert(&format!("https://example.com/{}", n));
+        }
+
+        assert!((0..1_000).all(|n| seen.contains(&format!("https://example.com/{}", n))));
+        assert!(seen.filters.len() > 1);
+    }
+
+    #[test]
+    fn scalable_bloom_filter_keeps_false_positive_rate_below_target() {
+        let mut seen = ScalableBloomFilter::new(1_000, 0.01);
+        for n in 0..10_000 {
+            seen.insert(&format!("https://example.com/{}", n));
+        }
+
+        let false_positives = (0..10_000)
+            .filter(|n| seen.contains(&format!("https://other.com/{}", n)))
+            .count();
+
+        assert!(false_positives < 100, "{} false positives", false_positives);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    dependencies::Frontier,
+    spilling_frontier::FrontierMetrics,
+    url::{filter_url, scope, Scope},
+    url_frontier::{Dequeue, Enqueue, Queue},
+};
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter, Result as FmtResult},
+    time::Duration,
+};
+use tokio::time::{sleep_until, Instant};
+use url::Url;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SiteOptions {
+    // Politeness delay between two requests to the site's host
+    pub delay: Duration,
+    // Pages requested from the site before the rest of its frontier is dropped
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct SiteStats {
+    pub requested: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+    // URLs dropped once the budget was used up
+    pub skipped: usize,
+}
+
+impl SiteStats {
+    pub fn budget_exhausted(&self) -> bool {
+        self.max_pages
+            .is_some_and(|max_pages| self.requested >= max_pages)
+    }
+}
+
+impl Display for SiteStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} request(s)", self.requested)?;
+        if let (true, Some(max_pages)) = (self.budget_exhausted(), self.max_pages) {
+            write!(
+                f,
+                ", budget of {} page(s) used up, {} URL(s) skipped",
+                max_pages, self.skipped
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// One site of a multi-site crawl, with a frontier of its own
+pub struct Site {
+    seed: String,
+    scope: Scope,
+    // Politeness is kept per host, sites with seeds on the same host wait for each other
+    host: String,
+    queue: Frontier<String>,
+    options: SiteOptions,
+    // Enqueued URLs not dequeued yet, reset once the queue turns out to be empty
+    pending: usize,
+    stats: SiteStats,
+}
+
+impl Site {
+    // `queue` should already hold the seed and have no delay of its own
+    pub fn new(seed: impl Into<String>, queue: Frontier<String>, options: SiteOptions) -> Self {
+        let seed = seed.into();
+
+        Site {
+            scope: scope(&seed),
+            host: Url::parse(&seed)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_owned()))
+                .unwrap_or_else(|| seed.clone()),
+            seed,
+            queue,
+            stats: SiteStats {
+                max_pages: options.max_pages,
+                ..SiteStats::default()
+            },
+            options,
+            pending: 1,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.pending > 0 && !self.stats.budget_exhausted()
+    }
+}
+
+// A frontier shared by several sites. Links go to the queue of the first site whose scope
+// they are in, and sites take turns so a large site can't starve the others. Each host
+// waits for the politeness delay of the site last requested from it, the frontier only
+// sleeps when every site with URLs left is waiting. URLs are expected to be enqueued once,
+// see Dependencies::build.
+pub struct SiteFrontier {
+    sites: Vec<Site>,
+    next: usize,
+    ready_at: HashMap<String, Instant>,
+}
+
+impl SiteFrontier {
+    pub fn new(sites: Vec<Site>) -> Self {
+        SiteFrontier {
+            sites,
+            next: 0,
+            ready_at: HashMap::new(),
+        }
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, SiteStats> {
+        self.sites
+            .iter()
+            .map(|site| (site.seed.clone(), site.stats.clone()))
+            .collect()
+    }
+
+    fn site_of(&self, url: &str) -> Option<usize> {
+        self.sites
+            .iter()
+            .position(|site| matches!(filter_url(url.to_owned(), site.scope.clone()), Ok(Some(_))))
+    }
+
+    // None for hosts that were not requested yet
+    fn ready_at(&self, site: &Site) -> Option<Instant> {
+        self.ready_at.get(&site.host).copied()
+    }
+
+    // The first active site that may be requested now, taking turns from `next`
+    fn ready_site(&self, now: Instant) -> Result<usize, Option<Instant>> {
+        let n = self.sites.len();
+        let active = (0..n)
+            .map(|i| (self.next + i) % n)
+            .filter(|i| self.sites[*i].is_active())
+            .collect::<Vec<_>>();
+
+        active
+            .iter()
+            .copied()
+            .find(|i| {
+                self.ready_at(&self.sites[*i])
+                    .is_none_or(|ready_at| ready_at <= now)
+            })
+            .ok_or_else(|| {
+                active
+                    .iter()
+                    .filter_map(|i| self.ready_at(&self.sites[*i]))
+                    .min()
+            })
+    }
+}
+
+impl Queue<String> for SiteFrontier {
+    // The metrics of every site's queue added up
+    fn metrics(&self) -> Option<FrontierMetrics> {
+        self.sites
+            .iter()
+            .filter_map(|site| site.queue.try_read().ok()?.metrics())
+            .reduce(|total, metrics| total + metrics)
+    }
+}
+
+#[async_trait]
+impl Dequeue<String> for SiteFrontier {
+    async fn dequeue(&mut self) -> Option<String> {
+        loop {
+            let i = match self.ready_site(Instant::now()) {
+                Ok(i) => i,
+                Err(Some(ready_at)) => {
+                    sleep_until(ready_at).await;
+                    continue;
+                }
+                Err(None) => return None,
+            };
+
+            let site = &mut self.sites[i];
+            let Some(url) = site.queue.write().await.dequeue().await else {
+                site.pending = 0;
+                continue;
+            };
+            site.pending = site.pending.saturating_sub(1);
+
+            site.stats.requested += 1;
+            let ready_at = Instant::now() + site.options.delay;
+            self.ready_at.insert(site.host.clone(), ready_at);
+            if site.stats.budget_exhausted() {
+                site.stats.skipped += site.pending;
+                info!("Budget of {} used up: {}", site.seed, site.stats);
+            }
+            self.next = (i + 1) % self.sites.len();
+
+            return Some(url);
+        }
+    }
+}
+
+impl Enqueue<String> for SiteFrontier {
+    fn enqueue(&mut self, value: String) {
+        if self.sites.is_empty() {
+            return;
+        }
+
+        let Some(i) = self.site_of(&value) else {
+            warn!("Dropping URL outside of every site: {}", value);
+            return;
+        };
+        let site = &mut self.sites[i];
+        if site.stats.budget_exhausted() {
+            site.stats.skipped += 1;
+            return;
+        }
+
+        // The queue is only locked by the frontier, unless whoever made the site kept a
+        // handle to it and holds it
+        match site.queue.try_write() {
+            Ok(mut queue) => {
+                queue.enqueue(value);
+                site.pending += 1;
+            }
+            Err(_) => warn!(
+                "Dropping URL, the queue of {} is locked: {}",
+                site.seed, value
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod site_frontier_tests {
+    use super::{Site, SiteFrontier, SiteOptions, SiteStats};
+    use crate::{
+        dependencies::{url_frontier, Frontier, UrlFrontierOptions},
+        url_frontier::{Dequeue, Enqueue},
+    };
+    use std::time::{Duration, Instant};
+
+    fn site(seed: &str, options: SiteOptions) -> Site {
+        let queue = url_frontier(UrlFrontierOptions {
+            delay_s: None,
+            uri: seed.to_owned(),
+        });
+        Site::new(seed, queue, options)
+    }
+
+    #[tokio::test]
+    async fn site_frontier_takes_turns_between_sites() {
+        let mut frontier = SiteFrontier::new(vec![
+            site("https://a.com/", SiteOptions::default()),
+            site("https://b.com/", SiteOptions::default()),
+        ]);
+        frontier.enqueue("https://a.com/1".to_owned());
+        frontier.enqueue("https://a.com/2".to_owned());
+        frontier.enqueue("https://b.com/1".to_owned());
+        frontier.enqueue("https://c.com/1".to_owned());
+
+        let mut order = vec![];
+        while let Some(url) = frontier.dequeue().await {
+            order.push(url);
+        }
+
+        assert_eq!(
+            order,
+            vec![
+                "https://a.com/",
+                "https://b.com/",
+                "https://a.com/1",
+                "https://b.com/1",
+                "https://a.com/2",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn site_frontier_drops_urls_while_a_queue_is_locked_elsewhere() {
+        let queue = url_frontier(UrlFrontierOptions {
+            delay_s: None,
+            uri: "https://a.com/".to_owned(),
+        });
+        let handle = Frontier(queue.0.clone());
+        let mut frontier = SiteFrontier::new(vec![Site::new(
+            "https://a.com/",
+            queue,
+            SiteOptions::default(),
+        )]);
+
+        {
+            let _locked = handle.write().await;
+            frontier.enqueue("https://a.com/1".to_owned());
+        }
+        frontier.enqueue("https://a.com/2".to_owned());
+
+        assert_eq!(frontier.sites[0].pending, 2);
+        assert_eq!(frontier.dequeue().await, Some("https://a.com/".to_owned()));
+        assert_eq!(frontier.dequeue().await, Some("https://a.com/2".to_owned()));
+        assert_eq!(frontier.dequeue().await, None);
+    }
+
+    #[tokio::test]
+    async fn site_frontier_stops_requesting_a_site_once_its_budget_is_used_up() {
+        let mut frontier = SiteFrontier::new(vec![site(
+            "https://a.com/",
+            SiteOptions {
+                max_pages: Some(2),
+                ..SiteOptions::default()
+            },
+        )]);
+        for n in 0..3 {
+            frontier.enqueue(format!("https://a.com/{}", n));
+        }
+
+        assert_eq!(frontier.dequeue().await, Some("https://a.com/".to_owned()));
+        assert_eq!(frontier.dequeue().await, Some("https://a.com/0".to_owned()));
+        frontier.enqueue("https://a.com/3".to_owned());
+        assert_eq!(frontier.dequeue().await, None);
+
+        assert_eq!(
+            frontier.stats()["https://a.com/"],
+            SiteStats {
+                requested: 2,
+                max_pages: Some(2),
+                skipped: 3,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn site_frontier_waits_for_each_sites_own_delay() {
+        let mut frontier = SiteFrontier::new(vec![
+            site(
+                "https://slow.com/",
+                SiteOptions {
+                    delay: Duration::from_millis(200),
+                    ..SiteOptions::default()
+                },
+            ),
+            site("https://fast.com/", SiteOptions::default()),
+        ]);
+        frontier.enqueue("https://slow.com/1".to_owned());
+        frontier.enqueue("https://fast.com/1".to_owned());
+        frontier.enqueue("https://fast.com/2".to_owned());
+        let start = Instant::now();
+
+        let mut order = vec![];
+        while let Some(url) = frontier.dequeue().await {
+            order.push(url);
+        }
+
+        assert_eq!(
+            order,
+            vec![
+                "https://slow.com/",
+                "https://fast.com/",
+                "https://fast.com/1",
+                "https://fast.com/2",
+                "https://slow.com/1",
+            ]
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn site_frontier_keeps_the_politeness_delay_per_host() {
+        let slow = SiteOptions {
+            delay: Duration::from_millis(200),
+            ..SiteOptions::default()
+        };
+        let mut frontier = SiteFrontier::new(vec![
+            site("https://a.com/docs/", slow.clone()),
+            site("https://a.com/blog/", slow),
+        ]);
+        let start = Instant::now();
+
+        assert_eq!(
+            frontier.dequeue().await,
+            Some("https://a.com/docs/".to_owned())
+        );
+        assert_eq!(
+            frontier.dequeue().await,
+            Some("https://a.com/blog/".to_owned())
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
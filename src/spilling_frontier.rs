@@ -0,0 +1,340 @@
+use crate::url_frontier::{Dequeue, Enqueue, Queue};
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Result, Write},
+    ops::Add,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::time::sleep;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FrontierMetrics {
+    // URLs waiting to be crawled, in memory and on disk
+    pub queued: usize,
+    pub in_memory: usize,
+    pub on_disk: usize,
+    pub segments_written: usize,
+    pub segments_read: usize,
+    // URLs that went through a segment file
+    pub spilled: usize,
+    // URLs given up on because segments could not be written and memory was full
+    #[serde(default)]
+    pub dropped: usize,
+}
+
+// Metrics of several frontiers together, e.g. of every site
+impl Add for FrontierMetrics {
+    type Output = FrontierMetrics;
+
+    fn add(self, other: FrontierMetrics) -> FrontierMetrics {
+        FrontierMetrics {
+            queued: self.queued + other.queued,
+            in_memory: self.in_memory + other.in_memory,
+            on_disk: self.on_disk + other.on_disk,
+            segments_written: self.segments_written + other.segments_written,
+            segments_read: self.segments_read + other.segments_read,
+            spilled: self.spilled + other.spilled,
+            dropped: self.dropped + other.dropped,
+        }
+    }
+}
+
+impl Display for FrontierMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} queued ({} in memory, {} on disk), {} spilled, {} segment(s) written, {} read",
+            self.queued,
+            self.in_memory,
+            self.on_disk,
+            self.spilled,
+            self.segments_written,
+            self.segments_read
+        )?;
+        if self.dropped > 0 {
+            write!(f, ", {} dropped", self.dropped)?;
+        }
+        Ok(())
+    }
+}
+
+// A FIFO frontier with bounded memory. URLs are dequeued from `head`; once it is full new
+// URLs collect in `tail`, which is written to a segment file on disk whenever it reaches
+// `buffer_size`. Segments are read back oldest first when `head` runs empty, so the order
+// is the same as with an unbounded queue. At most 2 * buffer_size URLs are kept in memory,
+// 3 * buffer_size while segments can't be written, URLs beyond that are dropped, as are
+// those of a segment that can't be read back.
+pub struct SpillingFrontier<T> {
+    head: VecDeque<T>,
+    segments: VecDeque<(PathBuf, usize)>,
+    tail: Vec<T>,
+    dir: PathBuf,
+    buffer_size: usize,
+    delay_s: Option<u64>,
+    metrics: FrontierMetrics,
+}
+
+impl<T: Serialize + DeserializeOwned> SpillingFrontier<T> {
+    pub fn new(dir: impl Into<PathBuf>, buffer_size: usize) -> Result<SpillingFrontier<T>> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(SpillingFrontier {
+            head: VecDeque::new(),
+            segments: VecDeque::new(),
+            tail: vec![],
+            dir,
+            buffer_size: buffer_size.max(1),
+            delay_s: None,
+            metrics: FrontierMetrics::default(),
+        })
+    }
+
+    pub fn delay_s(mut self, delay_s: u64) -> SpillingFrontier<T> {
+        self.delay_s = (delay_s > 0).then_some(delay_s);
+        self
+    }
+
+    pub fn value(mut self, value: T) -> SpillingFrontier<T> {
+        self.enqueue(value);
+        self
+    }
+
+    pub fn metrics(&self) -> FrontierMetrics {
+        let in_memory = self.head.len() + self.tail.len();
+        let on_disk = self.segments.iter().map(|(_, len)| len).sum::<usize>();
+
+        FrontierMetrics {
+            queued: in_memory + on_disk,
+            in_memory,
+            on_disk,
+            ..self.metrics
+        }
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        let path = self.dir.join(format!(
+            "segment-{:08}.jsonl",
+            self.metrics.segments_written
+        ));
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        for value in &self.tail {
+            serde_json::to_writer(&mut writer, value)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        self.segments.push_back((path, self.tail.len()));
+        self.metrics.segments_written += 1;
+        self.metrics.spilled += self.tail.len();
+        self.tail.clear();
+
+        info!("Spilled frontier segment: {}", self.metrics());
+        Ok(())
+    }
+
+    // Moves the oldest segment to `head`. What can't be read back counts as dropped, so
+    // that the crawl carries on with the rest of the frontier.
+    fn load(&mut self) {
+        let Some((path, len)) = self.segments.pop_front() else {
+            return;
+        };
+
+        match read_segment(&path) {
+            Ok(values) => {
+                self.metrics.dropped += len.saturating_sub(values.len());
+                self.metrics.segments_read += 1;
+                self.head.extend(values);
+                info!("Loaded frontier segment: {}", self.metrics());
+            }
+            Err(e) => {
+                self.metrics.dropped += len;
+                warn!(
+                    "Error reading frontier segment {} - {:?}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+
+        if let Err(e) = fs::remove_file(&path) {
+            warn!(
+                "Error removing frontier segment {} - {:?}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+// Lines that aren't a value are skipped, e.g. the last one of a segment cut short
+fn read_segment<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let mut values = vec![];
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(value) => values.push(value),
+            Err(e) => warn!("Skipping invalid line of {} - {}", path.display(), e),
+        }
+    }
+
+    Ok(values)
+}
+
+impl<T> Drop for SpillingFrontier<T> {
+    fn drop(&mut self) {
+        for (path, _) in &self.segments {
+            let _ = fs::remove_file(path);
+        }
+        let _ = fs::remove_dir(&self.dir);
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Send> Queue<T> for SpillingFrontier<T> {
+    fn metrics(&self) -> Option<FrontierMetrics> {
+        Some(SpillingFrontier::metrics(self))
+    }
+}
+
+#[async_trait]
+impl<T: Serialize + DeserializeOwned + Send> Dequeue<T> for SpillingFrontier<T> {
+    async fn dequeue(&mut self) -> Option<T> {
+        if let Some(delay_s) = self.delay_s {
+            sleep(Duration::from_secs(delay_s)).await;
+        }
+
+        while self.head.is_empty() {
+            if self.segments.is_empty() {
+                self.head.extend(self.tail.drain(..));
+                break;
+            }
+            self.load();
+        }
+
+        self.head.pop_front()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Enqueue<T> for SpillingFrontier<T> {
+    fn enqueue(&mut self, value: T) {
+        if self.segments.is_empty() && self.tail.is_empty() && self.head.len() < self.buffer_size {
+            self.head.push_back(value);
+            return;
+        }
+
+        // Segments have been failing for a while, memory is not given up for URLs either
+        if self.tail.len() >= 2 * self.buffer_size {
+            self.metrics.dropped += 1;
+            warn!("Frontier is full, dropping a URL: {}", self.metrics());
+            return;
+        }
+
+        self.tail.push(value);
+
+        // If the segment can't be written the URLs stay in memory and it is retried later
+        if self.tail.len() >= self.buffer_size {
+            if let Err(e) = self.spill() {
+                warn!("Error writing frontier segment - {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod spilling_frontier_tests {
+    use super::{FrontierMetrics, SpillingFrontier};
+    use crate::url_frontier::{Dequeue, Enqueue};
+    use std::fs;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn spilling_frontier_keeps_fifo_order_across_segments() {
+        let dir = std::env::temp_dir().join(format!("frontier-{}", Uuid::new_v4()));
+        let mut frontier = SpillingFrontier::new(&dir, 2).unwrap();
+
+        for n in 0..7 {
+            frontier.enqueue(n);
+        }
+
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        assert_eq!(
+            frontier.metrics(),
+            FrontierMetrics {
+                queued: 7,
+                in_memory: 3,
+                on_disk: 4,
+                segments_written: 2,
+                segments_read: 0,
+                spilled: 4,
+                dropped: 0,
+            }
+        );
+
+        let mut values = vec![];
+        frontier.enqueue(7);
+        while let Some(value) = frontier.dequeue().await {
+            values.push(value);
+            if value == 3 {
+                frontier.enqueue(8);
+            }
+        }
+
+        assert_eq!(values, (0..9).collect::<Vec<_>>());
+        assert_eq!(frontier.metrics().segments_read, 3);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        drop(frontier);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn spilling_frontier_bounds_memory_when_segments_cant_be_written() {
+        let dir = std::env::temp_dir().join(format!("frontier-{}", Uuid::new_v4()));
+        let mut frontier = SpillingFrontier::new(&dir, 2).unwrap();
+        fs::remove_dir(&dir).unwrap();
+
+        for n in 0..10 {
+            frontier.enqueue(n);
+        }
+
+        let metrics = frontier.metrics();
+        assert_eq!(metrics.in_memory, 6);
+        assert_eq!(metrics.segments_written, 0);
+        assert_eq!(metrics.dropped, 4);
+
+        let mut values = vec![];
+        while let Some(value) = frontier.dequeue().await {
+            values.push(value);
+        }
+        assert_eq!(values, (0..6).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn spilling_frontier_carries_on_past_segments_that_cant_be_read() {
+        let dir = std::env::temp_dir().join(format!("frontier-{}", Uuid::new_v4()));
+        let mut frontier = SpillingFrontier::new(&dir, 2).unwrap();
+
+        // 0 and 1 in memory, 2 and 3 then 4 and 5 on disk, 6 in memory again
+        for n in 0..7 {
+            frontier.enqueue(n);
+        }
+        fs::write(dir.join("segment-00000000.jsonl"), "not json\n3\n").unwrap();
+        fs::remove_file(dir.join("segment-00000001.jsonl")).unwrap();
+
+        let mut values = vec![];
+        while let Some(value) = frontier.dequeue().await {
+            values.push(value);
+        }
+
+        assert_eq!(values, vec![0, 1, 3, 6]);
+        assert_eq!(frontier.metrics().dropped, 3);
+        assert_eq!(frontier.metrics().queued, 0);
+    }
+}
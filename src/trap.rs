@@ -0,0 +1,275 @@
+use crate::record::stable_hash;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use url::Url;
+
+// Every limit is optional, None disables that heuristic
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TrapOptions {
+    pub max_url_length: Option<usize>,
+    // How often the same segment may appear in a path, e.g. 3 times for /a/b/a/b/a/b
+    pub max_repeated_segments: Option<usize>,
+    // Distinct query strings per path, e.g. /calendar?page=N
+    pub max_query_variants: Option<usize>,
+    pub max_pages_per_directory: Option<usize>,
+}
+
+impl TrapOptions {
+    // The limits of --traps, loose enough for regular sites
+    pub fn recommended() -> Self {
+        TrapOptions {
+            max_url_length: Some(2048),
+            max_repeated_segments: Some(3),
+            max_query_variants: Some(100),
+            max_pages_per_directory: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != TrapOptions::default()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "trap", rename_all = "snake_case")]
+pub enum Trap {
+    UrlTooLong {
+        length: usize,
+        max: usize,
+    },
+    RepeatedSegment {
+        segment: String,
+        count: usize,
+        max: usize,
+    },
+    QueryVariants {
+        path: String,
+        max: usize,
+    },
+    PagesPerDirectory {
+        directory: String,
+        max: usize,
+    },
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Trap::UrlTooLong { length, max } => {
+                write!(f, "URL is {} characters long, max {}", length, max)
+            }
+            Trap::RepeatedSegment {
+                segment,
+                count,
+                max,
+            } => write!(
+                f,
+                "path segment {:?} repeated {} times, max {}",
+                segment, count, max
+            ),
+            Trap::QueryVariants { path, max } => {
+                write!(f, "more than {} query strings for {}", max, path)
+            }
+            Trap::PagesPerDirectory { directory, max } => {
+                write!(f, "more than {} pages in {}", max, directory)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TrapTrip {
+    pub url: String,
+    pub source: String,
+    #[serde(flatten)]
+    pub trap: Trap,
+}
+
+impl Display for TrapTrip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} (linked from {}): {}",
+            self.url, self.source, self.trap
+        )
+    }
+}
+
+// Keeps the crawl out of endless URL spaces. URLs that were let through once always are,
+// so the per path and per directory caps only count distinct URLs.
+#[derive(Debug, Default)]
+pub struct TrapDetector {
+    options: TrapOptions,
+    query_variants: HashMap<String, HashSet<String>>,
+    directory_pages: HashMap<String, HashSet<String>>,
+    tripped: HashSet<String>,
+    trips: Vec<TrapTrip>,
+}
+
+impl TrapDetector {
+    pub fn new(options: TrapOptions) -> Self {
+        TrapDetector {
+            options,
+            ..TrapDetector::default()
+        }
+    }
+
+    pub fn trips(&self) -> &[TrapTrip] {
+        &self.trips
+    }
+
+    fn detect(&mut self, url: &str) -> Option<Trap> {
+        if let Some(max) = self.options.max_url_length.filter(|max| url.len() > *max) {
+            return Some(Trap::UrlTooLong {
+                length: url.len(),
+                max,
+            });
+        }
+
+        let parsed = Url::parse(url).ok()?;
+        let path = parsed.path();
+
+        if let Some(max) = self.options.max_repeated_segments {
+            let mut counts = HashMap::new();
+
+            for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+                let count = counts.entry(segment).or_insert(0);
+                *count += 1;
+
+                if *count > max {
+                    return Some(Trap::RepeatedSegment {
+                        segment: segment.to_owned(),
+                        count: *count,
+                        max,
+                    });
+                }
+            }
+        }
+
+        let page = format!("{}{}", &parsed[..url::Position::BeforePath], path);
+        let directory = page[..page.rfind('/').map_or(page.len(), |i| i + 1)].to_owned();
+        let url_hash = stable_hash(url.as_bytes());
+
+        if let (Some(max), Some(query)) = (self.options.max_query_variants, parsed.query()) {
+            let variants = self.query_variants.entry(page.clone()).or_default();
+
+            let query_hash = stable_hash(query.as_bytes());
+
+            if !variants.contains(&query_hash) && variants.len() >= max {
+                return Some(Trap::QueryVariants { path: page, max });
+            }
+            variants.insert(query_hash);
+        }
+
+        if let Some(max) = self.options.max_pages_per_directory {
+            let pages = self.directory_pages.entry(directory.clone()).or_default();
+
+            if !pages.contains(&url_hash) && pages.len() >= max {
+                return Some(Trap::PagesPerDirectory { directory, max });
+            }
+            pages.insert(url_hash);
+        }
+
+        None
+    }
+
+    // Whether a link found on `source` should be crawled, tripped traps are logged and kept
+    pub fn check(&mut self, url: &str, source: &str) -> bool {
+        if self.tripped.contains(url) {
+            return false;
+        }
+
+        let Some(trap) = self.detect(url) else {
+            return true;
+        };
+
+        let trip = TrapTrip {
+            url: url.to_owned(),
+            source: source.to_owned(),
+            trap,
+        };
+        warn!("Crawler trap: {}", trip);
+
+        self.tripped.insert(url.to_owned());
+        self.trips.push(trip);
+        false
+    }
+}
+
+#[cfg(test)]
+mod trap_tests {
+    use super::{Trap, TrapDetector, TrapOptions};
+
+    #[test]
+    fn trap_detector_limits_url_length_and_repeated_segments() {
+        let mut traps = TrapDetector::new(TrapOptions {
+            max_url_length: Some(40),
+            max_repeated_segments: Some(2),
+            ..TrapOptions::default()
+        });
+
+        assert!(traps.check("https://example.com/a/b/a/b", "/"));
+        assert!(!traps.check("https://example.com/a/b/a/b/a/b", "/"));
+        assert!(!traps.check("https://example.com/a-very-long-path-indeed", "/"));
+
+        assert_eq!(
+            traps.trips()[0].trap,
+            Trap::RepeatedSegment {
+                segment: "a".to_owned(),
+                count: 3,
+                max: 2
+            }
+        );
+        assert_eq!(
+            traps.trips()[1].trap,
+            Trap::UrlTooLong {
+                length: 43,
+                max: 40
+            }
+        );
+    }
+
+    #[test]
+    fn trap_detector_caps_query_variants_per_path() {
+        let mut traps = TrapDetector::new(TrapOptions {
+            max_query_variants: Some(2),
+            ..TrapOptions::default()
+        });
+
+        assert!(traps.check("https://example.com/calendar?page=1", "/"));
+        assert!(traps.check("https://example.com/calendar?page=2", "/"));
+        assert!(traps.check("https://example.com/calendar?page=1", "/"));
+        assert!(!traps.check("https://example.com/calendar?page=3", "/"));
+        assert!(!traps.check("https://example.com/calendar?page=3", "/other"));
+        assert!(traps.check("https://example.com/events?page=3", "/"));
+
+        assert_eq!(traps.trips().len(), 1);
+        assert_eq!(traps.trips()[0].source, "/");
+    }
+
+    #[test]
+    fn trap_detector_caps_pages_per_directory() {
+        let mut traps = TrapDetector::new(TrapOptions {
+            max_pages_per_directory: Some(2),
+            ..TrapOptions::default()
+        });
+
+        assert!(traps.check("https://example.com/blog/1", "/"));
+        assert!(traps.check("https://example.com/blog/2", "/"));
+        assert!(traps.check("https://example.com/blog/2", "/"));
+        assert!(!traps.check("https://example.com/blog/3", "/"));
+        assert!(traps.check("https://example.com/news/3", "/"));
+
+        assert_eq!(
+            traps.trips()[0].trap,
+            Trap::PagesPerDirectory {
+                directory: "https://example.com/blog/".to_owned(),
+                max: 2
+            }
+        );
+    }
+}
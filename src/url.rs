@@ -1,8 +1,10 @@
 use addr::parse_domain_name;
+use log::warn;
+use serde::{Deserialize, Serialize};
 use std::{
-    fmt::{Debug, Display},
-    hash::Hash,
-    sync::Arc,
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::{Arc, Mutex},
 };
 use url::{ParseError, Url};
 
@@ -12,6 +14,15 @@ pub enum Error {
     Other(String),
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::ParseError(e) => write!(f, "{}", e),
+            Error::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
 impl std::convert::From<addr::error::Error<'_>> for Error {
     fn from(err: addr::error::Error) -> Self {
         Error::Other(err.to_string())
@@ -24,15 +35,82 @@ impl std::convert::From<url::ParseError> for Error {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlKind {
+    Page,
+    Asset,
+    // mailto:, tel:, javascript:, data:, ftp: and the like, inventoried but never fetched
+    NonHttp,
+    Malformed,
+}
+
+const ASSET_EXTENSIONS: [&str; 22] = [
+    "css", "js", "mjs", "json", "xml", "txt", "png", "jpg", "jpeg", "gif", "webp", "svg", "ico",
+    "pdf", "zip", "gz", "mp3", "mp4", "webm", "woff", "woff2", "ttf",
+];
+
+pub fn url_kind(url: &str) -> UrlKind {
+    let Ok(parsed) = Url::parse(url) else {
+        return UrlKind::Malformed;
+    };
+
+    match parsed.scheme() {
+        "http" | "https" | "file" => {
+            let extension = parsed
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|file| file.rsplit_once('.'))
+                .map(|(_, extension)| extension.to_lowercase());
+
+            match extension {
+                Some(extension) if ASSET_EXTENSIONS.contains(&extension.as_str()) => UrlKind::Asset,
+                _ => UrlKind::Page,
+            }
+        }
+        _ => UrlKind::NonHttp,
+    }
+}
+
+impl UrlKind {
+    pub fn is_fetchable(&self) -> bool {
+        matches!(self, UrlKind::Page | UrlKind::Asset)
+    }
+}
+
+impl Display for UrlKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            UrlKind::Page => write!(f, "pages"),
+            UrlKind::Asset => write!(f, "assets"),
+            UrlKind::NonHttp => write!(f, "non-http"),
+            UrlKind::Malformed => write!(f, "malformed"),
+        }
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub struct UrlParts {
     sub_domain: String,
     root_domain: String,
+    directory: Option<String>,
 }
 
 pub fn url_parts(url: &str) -> Result<UrlParts, Error> {
     let parsed_url = Url::parse(url)?;
 
+    // file:// URLs have no domain, they are scoped to the directory they live in instead
+    if parsed_url.scheme() == "file" {
+        let path = parsed_url.path();
+        let directory = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+
+        return Ok(UrlParts {
+            sub_domain: "".to_owned(),
+            root_domain: "".to_owned(),
+            directory: Some(directory.to_owned()),
+        });
+    }
+
     if let Some(host) = parsed_url.host_str() {
         let domain = parse_domain_name(host)?;
 
@@ -51,36 +129,285 @@ pub fn url_parts(url: &str) -> Result<UrlParts, Error> {
         return Ok(UrlParts {
             sub_domain,
             root_domain,
+            directory: None,
         });
     }
 
     Ok(UrlParts {
         sub_domain: "".to_owned(),
         root_domain: "".to_owned(),
+        directory: None,
     })
 }
 
-pub fn process_url<T: Hash + Eq + Debug + Clone + Display>(
+// Query parameter names, ignoring case. A trailing * matches any name with that prefix,
+// e.g. utm_*
+fn param_matches(patterns: &[String], name: &str) -> bool {
+    let name = name.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(&prefix.to_lowercase()),
+            None => name.eq_ignore_ascii_case(pattern),
+        })
+}
+
+fn param_name(param: &str) -> &str {
+    param.split('=').next().unwrap_or_default()
+}
+
+// Removes query parameters, and ;name=value path parameters if `path_params` is set, for
+// which `remove` is true. The rest of the URL is kept byte for byte.
+fn remove_params(url: &str, path_params: bool, remove: impl Fn(&str) -> bool) -> String {
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+
+    let mut result = path
+        .split('/')
+        .map(|segment| {
+            let mut parts = segment.split(';');
+            let mut segment = parts.next().unwrap_or_default().to_owned();
+            for param in parts.filter(|param| !path_params || !remove(param_name(param))) {
+                segment.push(';');
+                segment.push_str(param);
+            }
+            segment
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if let Some(query) = query {
+        let params = query
+            .split('&')
+            .filter(|param| param.is_empty() || !remove(param_name(param)))
+            .collect::<Vec<_>>();
+
+        if !params.iter().all(|param| param.is_empty()) {
+            result.push('?');
+            result.push_str(&params.join("&"));
+        }
+    }
+
+    if let Some(fragment) = fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+
+    result
+}
+
+// Query parameter rules applied to every seed and link found. Parameters matching `strip`
+// are removed, path parameters such as ;jsessionid= included, and when `keep_only` is set
+// every other query parameter is removed too. Parameters
+// matching `ignore` stay in the URL but don't make it a new page: the first URL found
+// that only differs in those parameters is used for all of them.
+#[derive(Debug)]
+pub struct QueryRules {
+    pub strip: Vec<String>,
+    pub keep_only: Option<Vec<String>>,
+    pub ignore: Vec<String>,
+    variants: Mutex<HashMap<String, String>>,
+    originals: Mutex<HashMap<String, String>>,
+    // Entries each map holds at most. Past that, URLs differing in ignored parameters are
+    // new pages again and rewritten URLs don't remember their original.
+    limit: usize,
+}
+
+// Keeps the memory of both maps to a few hundred MB on crawls of millions of URLs
+pub const MAX_TRACKED_URLS: usize = 1_000_000;
+
+impl Default for QueryRules {
+    fn default() -> Self {
+        QueryRules {
+            strip: vec![],
+            keep_only: None,
+            ignore: vec![],
+            variants: Mutex::default(),
+            originals: Mutex::default(),
+            limit: MAX_TRACKED_URLS,
+        }
+    }
+}
+
+// Inserts unless the map is full, warning once when it fills up
+fn track(map: &mut HashMap<String, String>, limit: usize, key: String, value: String) {
+    if map.len() >= limit {
+        return;
+    }
+    map.insert(key, value);
+    if map.len() == limit {
+        warn!("Tracking {} rewritten URLs, not tracking any more", limit);
+    }
+}
+
+pub const TRACKING_PARAMS: [&str; 7] = [
+    "utm_*", "gclid", "dclid", "fbclid", "msclkid", "mc_cid", "mc_eid",
+];
+pub const SESSION_PARAMS: [&str; 4] = ["jsessionid", "phpsessid", "aspsessionid*", "sessionid"];
+
+impl QueryRules {
+    pub fn new(strip: Vec<String>, keep_only: Option<Vec<String>>, ignore: Vec<String>) -> Self {
+        QueryRules {
+            strip,
+            keep_only,
+            ignore,
+            ..QueryRules::default()
+        }
+    }
+
+    // Strips common tracking and session ID parameters
+    pub fn preset() -> Self {
+        QueryRules::new(
+            TRACKING_PARAMS
+                .iter()
+                .chain(SESSION_PARAMS.iter())
+                .map(|param| param.to_string())
+                .collect(),
+            None,
+            vec![],
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strip.is_empty() && self.keep_only.is_none() && self.ignore.is_empty()
+    }
+
+    pub fn apply(&self, url: String) -> String {
+        if self.is_empty() {
+            return url;
+        }
+
+        let stripped = remove_params(&url, true, |name| param_matches(&self.strip, name));
+        let stripped = match &self.keep_only {
+            Some(keep_only) => {
+                remove_params(&stripped, false, |name| !param_matches(keep_only, name))
+            }
+            None => stripped,
+        };
+
+        let result = if self.ignore.is_empty() {
+            stripped
+        } else {
+            let key = remove_params(&stripped, true, |name| param_matches(&self.ignore, name));
+            let mut variants = self.variants.lock().unwrap();
+            match variants.get(&key) {
+                Some(first) => first.clone(),
+                None => {
+                    track(&mut variants, self.limit, key, stripped.clone());
+                    stripped
+                }
+            }
+        };
+
+        if result != url {
+            let mut originals = self.originals.lock().unwrap();
+            if !originals.contains_key(&result) {
+                track(&mut originals, self.limit, result.clone(), url);
+            }
+        }
+
+        result
+    }
+
+    // The URL as first found, for URLs that were rewritten by the rules
+    pub fn original(&self, url: &str) -> Option<String> {
+        let originals = self.originals.lock().unwrap();
+        originals.get(url).cloned()
+    }
+
+    pub fn originals(&self) -> BTreeMap<String, String> {
+        let originals = self.originals.lock().unwrap();
+        originals
+            .iter()
+            .map(|(url, original)| (url.clone(), original.clone()))
+            .collect()
+    }
+}
+
+pub fn process_url(
     url: String,
     original_url: impl AsRef<str>,
-) -> T {
-    if Url::parse(&url) == Err(ParseError::RelativeUrlWithoutBase) {
-        let original_url = Url::parse(original_url.as_ref()).unwrap();
-        let absolute_url = original_url.join(&url);
-        absolute_url.unwrap().as_str().to_owned()
-    } else {
-        url
-    }
+    rules: &QueryRules,
+) -> Result<String, Error> {
+    let url = match Url::parse(&url) {
+        Ok(_) => url,
+        Err(ParseError::RelativeUrlWithoutBase) => {
+            let original_url = Url::parse(original_url.as_ref())?;
+            original_url.join(&url)?.as_str().to_owned()
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(rules.apply(url))
 }
 
-pub fn filter_url(url: String, original_url_parts: Arc<Result<UrlParts, Error>>) -> Option<String> {
+// Ok(None) for URLs out of the seed's scope, errors only for URLs that can't be parsed.
+// Hosts the domain parser rejects, such as IP addresses, are compared as they are.
+pub fn filter_url(
+    url: String,
+    original_url_parts: Arc<Result<UrlParts, Error>>,
+) -> Result<Option<String>, Error> {
+    // Hostless URLs like mailto: would otherwise match a hostless seed
+    if !url_kind(&url).is_fetchable() {
+        return match Url::parse(&url) {
+            Ok(_) => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+
     let current_url_parts = url_parts(&url);
-    let original_url_parts = original_url_parts.clone();
-    if current_url_parts == *original_url_parts {
-        Some(url.to_owned())
-    } else {
-        None
+    if let Err(Error::ParseError(e)) = current_url_parts {
+        return Err(Error::ParseError(e));
+    }
+
+    if let Ok(UrlParts {
+        directory: Some(directory),
+        ..
+    }) = &*original_url_parts
+    {
+        return Ok(match current_url_parts {
+            Ok(UrlParts {
+                directory: Some(current_directory),
+                ..
+            }) if current_directory.starts_with(directory.as_str()) => Some(url),
+            _ => None,
+        });
     }
+
+    Ok((current_url_parts == *original_url_parts).then_some(url))
+}
+
+// The scope of one seed, as used by filter_url
+pub type Scope = Arc<Result<UrlParts, Error>>;
+
+pub fn scope(seed: &str) -> Scope {
+    Arc::new(url_parts(seed))
+}
+
+// The scope of the site `url` belongs to, i.e. the first of `scopes` it is in. URLs in none
+// of them, e.g. pages of a previous crawl, get every scope.
+pub fn site_scope<'a>(url: &str, scopes: &'a [Scope]) -> &'a [Scope] {
+    scopes
+        .iter()
+        .position(|scope| matches!(filter_url(url.to_owned(), scope.clone()), Ok(Some(_))))
+        .map_or(scopes, |i| &scopes[i..=i])
+}
+
+// Links in the scope of any of `scopes`, see site_scope for the scope of a single site
+pub fn filter_url_in(url: String, scopes: &[Scope]) -> Result<Option<String>, Error> {
+    for scope in scopes {
+        if let Some(url) = filter_url(url.clone(), scope.clone())? {
+            return Ok(Some(url));
+        }
+    }
+
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -91,7 +418,10 @@ mod link_tests {
     use url::ParseError;
 
     use super::url_parts;
-    use crate::url::{filter_url, process_url, UrlParts};
+    use crate::url::{
+        filter_url, filter_url_in, process_url, scope, site_scope, url_kind, QueryRules, UrlKind,
+        UrlParts,
+    };
 
     #[test]
     fn url_parts_constructs_url_with_www_correctly() {
@@ -100,6 +430,7 @@ mod link_tests {
         let expected = Ok(UrlParts {
             sub_domain: "www".to_owned(),
             root_domain: "github.com".to_owned(),
+            directory: None,
         });
 
         assert_eq!(result, expected);
@@ -112,6 +443,7 @@ mod link_tests {
         let expected = Ok(UrlParts {
             sub_domain: "www".to_owned(),
             root_domain: "github.com".to_owned(),
+            directory: None,
         });
 
         assert_eq!(result, expected);
@@ -124,6 +456,7 @@ mod link_tests {
         let expected = Ok(UrlParts {
             sub_domain: "".to_owned(),
             root_domain: "".to_owned(),
+            directory: None,
         });
 
         assert_eq!(result, expected);
@@ -143,9 +476,9 @@ mod link_tests {
         let link = "/users".to_owned();
         let expected = "https://github.com/users".to_owned();
 
-        let actual = process_url(link, "https://github.com");
+        let actual = process_url(link, "https://github.com", &QueryRules::default());
 
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Ok(expected));
     }
 
     #[test]
@@ -154,10 +487,11 @@ mod link_tests {
         let original_url_parts = UrlParts {
             sub_domain: "www".to_owned(),
             root_domain: "google.com".to_owned(),
+            directory: None,
         };
 
         let actual = filter_url(link, Arc::new(Ok(original_url_parts)));
-        let expected = None;
+        let expected = Ok(None);
 
         assert_eq!(actual, expected);
     }
@@ -168,11 +502,211 @@ mod link_tests {
         let original_url_parts = UrlParts {
             sub_domain: "www".to_owned(),
             root_domain: "google.com".to_owned(),
+            directory: None,
         };
 
         let actual = filter_url(link, Arc::new(Ok(original_url_parts)));
-        let expected = Some("https://google.com".to_owned());
+        let expected = Ok(Some("https://google.com".to_owned()));
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn url_parts_scopes_file_urls_to_their_directory() {
+        let result = url_parts("file:///site/blog/index.html");
+
+        let expected = Ok(UrlParts {
+            sub_domain: "".to_owned(),
+            root_domain: "".to_owned(),
+            directory: Some("/site/blog/".to_owned()),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn filter_url_keeps_file_urls_under_the_seed_directory_only() {
+        let original_url_parts = Arc::new(url_parts("file:///site/index.html"));
+
+        let inside = filter_url("file:///site/blog/".to_owned(), original_url_parts.clone());
+        let outside = filter_url("file:///other/".to_owned(), original_url_parts.clone());
+        let http = filter_url("https://google.com".to_owned(), original_url_parts);
+
+        assert_eq!(inside, Ok(Some("file:///site/blog/".to_owned())));
+        assert_eq!(outside, Ok(None));
+        assert_eq!(http, Ok(None));
+    }
+
+    #[test]
+    fn site_scope_is_the_scope_of_the_site_a_page_belongs_to() {
+        let scopes = [scope("https://a.com/"), scope("https://b.com/")];
+
+        let a = site_scope("https://a.com/page", &scopes);
+        let elsewhere = site_scope("https://c.com/", &scopes);
+
+        assert_eq!(a.len(), 1);
+        assert_eq!(filter_url_in("https://b.com/".to_owned(), a), Ok(None));
+        assert_eq!(elsewhere.len(), 2);
+    }
+
+    #[test]
+    fn process_url_strips_tracking_and_session_parameters() {
+        let rules = QueryRules::preset();
+
+        let actual = process_url(
+            "/shop;jsessionid=A1?id=7&utm_source=x&UTM_Medium=y&fbclid=z#top".to_owned(),
+            "https://example.com/",
+            &rules,
+        )
+        .unwrap();
+
+        assert_eq!(actual, "https://example.com/shop?id=7#top");
+        assert_eq!(
+            rules.original(&actual),
+            Some(
+                "https://example.com/shop;jsessionid=A1?id=7&utm_source=x&UTM_Medium=y&fbclid=z#top"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn process_url_matches_parameter_prefixes_ignoring_case() {
+        let rules = QueryRules::new(vec!["UTM_*".to_owned()], None, vec![]);
+
+        let actual = process_url(
+            "https://example.com/?utm_source=x&Utm_Medium=y&id=7".to_owned(),
+            "https://example.com/",
+            &rules,
+        );
+
+        assert_eq!(actual, Ok("https://example.com/?id=7".to_owned()));
+    }
+
+    #[test]
+    fn process_url_keeps_only_listed_parameters() {
+        let rules = QueryRules::new(vec![], Some(vec!["page".to_owned()]), vec![]);
+
+        let with_other = process_url(
+            "https://example.com/?sort=asc&page=2".to_owned(),
+            "https://example.com/",
+            &rules,
+        )
+        .unwrap();
+        let with_path_params = process_url(
+            "https://example.com/a;v=1?page=2".to_owned(),
+            "https://example.com/",
+            &rules,
+        )
+        .unwrap();
+        let without_params = process_url(
+            "https://example.com/?sort=asc".to_owned(),
+            "https://example.com/",
+            &rules,
+        )
+        .unwrap();
+
+        assert_eq!(with_other, "https://example.com/?page=2");
+        assert_eq!(with_path_params, "https://example.com/a;v=1?page=2");
+        assert_eq!(without_params, "https://example.com/");
+        assert_eq!(rules.original("https://example.com/?page=3"), None);
+    }
+
+    #[test]
+    fn process_url_maps_urls_differing_in_ignored_parameters_to_the_first_one() {
+        let rules = QueryRules::new(vec![], None, vec!["ref".to_owned()]);
+
+        let first = process_url("/a?ref=home".to_owned(), "https://example.com/", &rules);
+        let second = process_url("/a?ref=footer".to_owned(), "https://example.com/", &rules);
+        let other = process_url("/b?ref=footer".to_owned(), "https://example.com/", &rules);
+
+        assert_eq!(first, Ok("https://example.com/a?ref=home".to_owned()));
+        assert_eq!(second, Ok("https://example.com/a?ref=home".to_owned()));
+        assert_eq!(other, Ok("https://example.com/b?ref=footer".to_owned()));
+    }
+
+    #[test]
+    fn query_rules_track_a_limited_number_of_urls() {
+        let rules = QueryRules {
+            limit: 2,
+            ..QueryRules::new(vec!["utm_*".to_owned()], None, vec!["ref".to_owned()])
+        };
+
+        for page in ["a", "b", "c"] {
+            rules.apply(format!("https://example.com/{}?ref=1&utm_source=x", page));
+        }
+        let tracked = rules.apply("https://example.com/b?ref=2".to_owned());
+        let untracked = rules.apply("https://example.com/c?ref=2".to_owned());
+
+        assert_eq!(tracked, "https://example.com/b?ref=1");
+        assert_eq!(untracked, "https://example.com/c?ref=2");
+        assert_eq!(rules.originals().len(), 2);
+        assert_eq!(rules.original("https://example.com/c?ref=1"), None);
+    }
+
+    #[test]
+    fn url_kind_classifies_pages_assets_other_schemes_and_malformed_urls() {
+        assert_eq!(url_kind("https://example.com/about"), UrlKind::Page);
+        assert_eq!(url_kind("https://example.com/docs/"), UrlKind::Page);
+        assert_eq!(url_kind("http://example.com/logo.PNG?v=2"), UrlKind::Asset);
+        assert_eq!(url_kind("file:///style.css"), UrlKind::Asset);
+        assert_eq!(url_kind("mailto:someone@example.com"), UrlKind::NonHttp);
+        assert_eq!(url_kind("tel:+441234567890"), UrlKind::NonHttp);
+        assert_eq!(url_kind("javascript:void(0)"), UrlKind::NonHttp);
+        assert_eq!(url_kind("http://exa mple.com"), UrlKind::Malformed);
+    }
+
+    #[test]
+    fn filter_url_filters_out_non_http_urls_for_hostless_seeds() {
+        let original_url_parts = Arc::new(url_parts("data:text/plain,seed"));
+
+        let result = filter_url("mailto:someone@example.com".to_owned(), original_url_parts);
+
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn process_url_returns_error_for_invalid_urls_and_bases() {
+        let rules = QueryRules::default();
+
+        let invalid_url = process_url("http://exa mple.com".to_owned(), "https://a.com", &rules);
+        let invalid_base = process_url("/users".to_owned(), "not a url", &rules);
+
+        assert_eq!(
+            invalid_url,
+            Err(Error::ParseError(ParseError::InvalidDomainCharacter))
+        );
+        assert_eq!(
+            invalid_base,
+            Err(Error::ParseError(ParseError::RelativeUrlWithoutBase))
+        );
+    }
+
+    #[test]
+    fn filter_url_returns_error_for_urls_that_cannot_be_parsed() {
+        let original_url_parts = Arc::new(url_parts("https://google.com"));
+
+        let result = filter_url("http://[::1".to_owned(), original_url_parts);
+
+        assert_eq!(
+            result,
+            Err(Error::ParseError(ParseError::InvalidIpv6Address))
+        );
+    }
+
+    #[test]
+    fn filter_url_in_keeps_links_in_the_scope_of_any_seed() {
+        let scopes = [
+            scope("https://example.com/"),
+            scope("file:///site/index.html"),
+        ];
+
+        let first = filter_url_in("https://www.example.com/a".to_owned(), &scopes);
+        let second = filter_url_in("file:///site/b.html".to_owned(), &scopes);
+        let neither = filter_url_in("https://example.org/".to_owned(), &scopes);
+
+        assert_eq!(first, Ok(Some("https://www.example.com/a".to_owned())));
+        assert_eq!(second, Ok(Some("file:///site/b.html".to_owned())));
+        assert_eq!(neither, Ok(None));
+    }
 }
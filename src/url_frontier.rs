@@ -1,9 +1,15 @@
+use crate::spilling_frontier::FrontierMetrics;
 use async_trait::async_trait;
 use crossbeam_queue::SegQueue;
 use std::{sync::Arc, time::Duration};
 use tokio::time::sleep;
 
-pub trait Queue<T>: Enqueue<T> + Dequeue<T> {}
+pub trait Queue<T>: Enqueue<T> + Dequeue<T> {
+    // Only frontiers that spill to disk keep metrics
+    fn metrics(&self) -> Option<FrontierMetrics> {
+        None
+    }
+}
 
 #[async_trait]
 pub trait Dequeue<T> {
@@ -0,0 +1,435 @@
+use crate::{
+    fetch::{Headers, Response},
+    output::ResponseWriter,
+};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use std::{
+    fs::{self, File},
+    io::{Error, ErrorKind, Read, Result, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use url::Url;
+use uuid::Uuid;
+
+pub const WARC_VERSION: &str = "WARC/1.1";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WarcRecord {
+    pub headers: Headers,
+    pub block: Vec<u8>,
+}
+
+impl WarcRecord {
+    pub fn new(warc_type: &str, content_type: &str, block: Vec<u8>) -> Self {
+        WarcRecord {
+            headers: vec![
+                ("WARC-Type".to_owned(), warc_type.to_owned()),
+                ("WARC-Record-ID".to_owned(), record_id()),
+                (
+                    "WARC-Date".to_owned(),
+                    humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                ),
+                ("Content-Type".to_owned(), content_type.to_owned()),
+            ],
+            block,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!("{}\r\n", WARC_VERSION).into_bytes();
+
+        for (name, value) in &self.headers {
+            bytes.extend(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        bytes.extend(format!("Content-Length: {}\r\n\r\n", self.block.len()).as_bytes());
+        bytes.extend(&self.block);
+        bytes.extend(b"\r\n\r\n");
+
+        bytes
+    }
+}
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", Uuid::new_v4())
+}
+
+fn http_headers(start_line: String, headers: &[(String, String)]) -> Vec<u8> {
+    let mut bytes = format!("{}\r\n", start_line).into_bytes();
+
+    for (name, value) in headers {
+        bytes.extend(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    bytes.extend(b"\r\n");
+
+    bytes
+}
+
+// The request as it was made, for the URL requested rather than the one redirected to
+pub fn request_record(response: &Response) -> WarcRecord {
+    let (target, host) = match Url::parse(response.requested()) {
+        Ok(url) => {
+            let target = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_owned(),
+            };
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+                None => url.host_str().unwrap_or_default().to_owned(),
+            };
+            (target, host)
+        }
+        Err(_) => ("/".to_owned(), "".to_owned()),
+    };
+
+    let mut headers = vec![("host".to_owned(), host)];
+    headers.extend(response.request_headers.iter().cloned());
+
+    let block = http_headers(format!("GET {} {}", target, response.version), &headers);
+
+    WarcRecord::new("request", "application/http;msgtype=request", block)
+        .header("WARC-Target-URI", response.requested())
+}
+
+pub fn response_record(response: &Response) -> WarcRecord {
+    let mut block = http_headers(
+        format!(
+            "{} {} {}",
+            response.version, response.status, response.reason
+        ),
+        &response.headers,
+    );
+    block.extend(&response.body);
+
+    WarcRecord::new("response", "application/http;msgtype=response", block)
+        .header("WARC-Target-URI", &response.url)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_owned())
+}
+
+fn split_line(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.windows(2).position(|window| window == b"\r\n")?;
+    let line = std::str::from_utf8(&bytes[..end]).ok()?;
+    Some((line, &bytes[end + 2..]))
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Option<(Headers, &[u8])> {
+    let mut headers = vec![];
+
+    loop {
+        let (line, rest) = split_line(bytes)?;
+        bytes = rest;
+
+        if line.is_empty() {
+            return Some((headers, bytes));
+        }
+
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+}
+
+// Reads all records of a WARC file, compressed (gzip-per-record) or not
+pub fn read_records(path: impl AsRef<Path>) -> Result<Vec<WarcRecord>> {
+    let raw = fs::read(path)?;
+    let bytes = if raw.starts_with(&[0x1f, 0x8b]) {
+        let mut bytes = vec![];
+        MultiGzDecoder::new(&raw[..]).read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        raw
+    };
+
+    let mut records = vec![];
+    let mut rest = &bytes[..];
+
+    while !rest.is_empty() {
+        let (version, after_version) = split_line(rest).ok_or(invalid("Truncated WARC record"))?;
+        if !version.starts_with("WARC/") {
+            return Err(invalid("Missing WARC version line"));
+        }
+
+        let (mut headers, after_headers) =
+            parse_headers(after_version).ok_or(invalid("Invalid WARC record headers"))?;
+
+        let length_position = headers
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+            .ok_or(invalid("Missing WARC Content-Length"))?;
+        let (_, length) = headers.remove(length_position);
+        let length = length
+            .parse::<usize>()
+            .map_err(|_| invalid("Invalid WARC Content-Length"))?;
+
+        if after_headers.len() < length {
+            return Err(invalid("Truncated WARC record block"));
+        }
+
+        records.push(WarcRecord {
+            headers,
+            block: after_headers[..length].to_vec(),
+        });
+
+        rest = after_headers[length..]
+            .strip_prefix(b"\r\n\r\n")
+            .unwrap_or(&after_headers[length..]);
+    }
+
+    Ok(records)
+}
+
+// Turns a WARC response record back into the Response it was created from
+pub fn parse_response(record: &WarcRecord) -> Option<Response> {
+    if record.get("WARC-Type") != Some("response") {
+        return None;
+    }
+
+    let (status_line, rest) = split_line(&record.block)?;
+    let mut status_line = status_line.splitn(3, ' ');
+    let version = status_line.next()?.to_owned();
+    let status = status_line.next()?.parse::<u16>().ok()?;
+    let reason = status_line.next().unwrap_or_default().to_owned();
+    let (headers, body) = parse_headers(rest)?;
+
+    Some(Response {
+        url: record.get("WARC-Target-URI")?.to_owned(),
+        version,
+        status,
+        reason,
+        headers,
+        body: body.to_vec(),
+        ..Response::default()
+    })
+}
+
+// Writes gzip-per-record WARC files named <prefix>-00000.warc.gz, <prefix>-00001.warc.gz etc.
+// A new file is started once the current one has reached max_size bytes.
+pub struct WarcWriter {
+    prefix: PathBuf,
+    max_size: u64,
+    index: usize,
+    file: Option<File>,
+    written: u64,
+}
+
+impl WarcWriter {
+    pub fn new(prefix: impl Into<PathBuf>, max_size: u64) -> Self {
+        WarcWriter {
+            prefix: prefix.into(),
+            max_size,
+            index: 0,
+            file: None,
+            written: 0,
+        }
+    }
+
+    pub fn path(&self, index: usize) -> PathBuf {
+        let mut path = self.prefix.clone().into_os_string();
+        path.push(format!("-{:05}.warc.gz", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        if self.file.is_some() {
+            self.index += 1;
+        }
+
+        self.file = Some(File::create(self.path(self.index))?);
+        self.written = 0;
+
+        let info = WarcRecord::new(
+            "warcinfo",
+            "application/warc-fields",
+            format!(
+                "software: url-crawler/{}\r\nformat: WARC File Format 1.1\r\n",
+                env!("CARGO_PKG_VERSION")
+            )
+            .into_bytes(),
+        )
+        .header("WARC-Filename", &self.path(self.index).to_string_lossy());
+        self.write_gzipped(&info)
+    }
+
+    fn write_gzipped(&mut self, record: &WarcRecord) -> Result<()> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&record.to_bytes())?;
+        let compressed = encoder.finish()?;
+
+        if let Some(file) = &mut self.file {
+            file.write_all(&compressed)?;
+            file.flush()?;
+            self.written += compressed.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_record(&mut self, record: &WarcRecord) -> Result<()> {
+        if self.file.is_none() || self.written >= self.max_size {
+            self.rotate()?;
+        }
+
+        self.write_gzipped(record)
+    }
+}
+
+// A request and its response always go to the same file
+impl ResponseWriter for WarcWriter {
+    fn write(&mut self, response: &Response) -> Result<()> {
+        let request = request_record(response);
+        let request_id = request.get("WARC-Record-ID").unwrap_or_default().to_owned();
+
+        self.write_record(&request)?;
+        self.write_gzipped(&response_record(response).header("WARC-Concurrent-To", &request_id))
+    }
+}
+
+#[cfg(test)]
+mod warc_tests {
+    use super::{
+        parse_response, read_records, request_record, response_record, WarcRecord, WarcWriter,
+    };
+    use crate::{fetch::Response, output::ResponseWriter};
+    use flate2::read::MultiGzDecoder;
+    use std::{fs, io::Read};
+    use uuid::Uuid;
+
+    fn response() -> Response {
+        Response {
+            url: "https://example.com/a?b=c".to_owned(),
+            version: "HTTP/1.1".to_owned(),
+            status: 200,
+            reason: "OK".to_owned(),
+            headers: vec![("content-type".to_owned(), "text/html".to_owned())],
+            body: b"<p>Hi</p>".to_vec(),
+            ..Response::default()
+        }
+    }
+
+    fn read(path: std::path::PathBuf) -> String {
+        let mut output = String::new();
+        MultiGzDecoder::new(fs::File::open(&path).unwrap())
+            .read_to_string(&mut output)
+            .unwrap();
+        fs::remove_file(path).unwrap();
+        output
+    }
+
+    #[test]
+    fn warc_record_serialises_headers_and_block() {
+        let record = WarcRecord {
+            headers: vec![("WARC-Type".to_owned(), "response".to_owned())],
+            block: b"body".to_vec(),
+        };
+
+        assert_eq!(
+            record.to_bytes(),
+            b"WARC/1.1\r\nWARC-Type: response\r\nContent-Length: 4\r\n\r\nbody\r\n\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn response_record_contains_http_status_line_headers_and_body() {
+        let record = response_record(&response());
+        let block = String::from_utf8(record.block.clone()).unwrap();
+
+        assert_eq!(record.get("WARC-Type"), Some("response"));
+        assert_eq!(
+            record.get("WARC-Target-URI"),
+            Some("https://example.com/a?b=c")
+        );
+        assert_eq!(
+            block,
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n<p>Hi</p>"
+        );
+    }
+
+    #[test]
+    fn warc_writer_writes_request_and_response_records() {
+        let prefix = std::env::temp_dir().join(format!("warc-{}", Uuid::new_v4()));
+        let mut writer = WarcWriter::new(&prefix, 1024 * 1024);
+
+        writer.write(&response()).unwrap();
+
+        let output = read(writer.path(0));
+
+        assert_eq!(output.matches("WARC/1.1\r\n").count(), 3);
+        assert!(output.contains("WARC-Type: warcinfo"));
+        assert!(output.contains("GET /a?b=c HTTP/1.1\r\nhost: example.com\r\n"));
+        assert!(output.contains("WARC-Concurrent-To: <urn:uuid:"));
+    }
+
+    #[test]
+    fn request_record_targets_the_requested_url_of_a_redirect() {
+        let redirected = Response {
+            requested_url: "http://example.com/old".to_owned(),
+            ..response()
+        };
+
+        let request = request_record(&redirected);
+        let block = String::from_utf8(request.block.clone()).unwrap();
+
+        assert_eq!(
+            request.get("WARC-Target-URI"),
+            Some("http://example.com/old")
+        );
+        assert!(block.starts_with("GET /old HTTP/1.1\r\nhost: example.com\r\n"));
+        assert_eq!(
+            response_record(&redirected).get("WARC-Target-URI"),
+            Some("https://example.com/a?b=c")
+        );
+    }
+
+    #[test]
+    fn warc_writer_rotates_files_once_max_size_is_reached() {
+        let prefix = std::env::temp_dir().join(format!("warc-{}", Uuid::new_v4()));
+        let mut writer = WarcWriter::new(&prefix, 1);
+
+        writer.write(&response()).unwrap();
+        writer.write(&response()).unwrap();
+
+        let first = read(writer.path(0));
+        let second = read(writer.path(1));
+
+        for output in [first, second] {
+            assert!(output.contains("WARC-Type: warcinfo"));
+            assert!(output.contains("WARC-Type: request"));
+            assert!(output.contains("WARC-Type: response"));
+        }
+        assert!(!writer.path(2).exists());
+    }
+
+    #[test]
+    fn read_records_reads_back_written_records() {
+        let prefix = std::env::temp_dir().join(format!("warc-{}", Uuid::new_v4()));
+        let mut writer = WarcWriter::new(&prefix, 1024 * 1024);
+
+        writer.write(&response()).unwrap();
+
+        let records = read_records(writer.path(0)).unwrap();
+        fs::remove_file(writer.path(0)).unwrap();
+
+        let types = records
+            .iter()
+            .map(|record| record.get("WARC-Type").unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(types, vec!["warcinfo", "request", "response"]);
+        assert_eq!(parse_response(&records[2]), Some(response()));
+        assert_eq!(parse_response(&records[1]), None);
+    }
+}